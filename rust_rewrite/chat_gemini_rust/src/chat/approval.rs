@@ -0,0 +1,56 @@
+use crate::config::Settings;
+use colored::Colorize;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::io::{self, Write};
+
+/// 比照 aichat 對「執行類」工具的判斷方式：工具名稱 (`__` 之後的那段) 以 `may_`
+/// 開頭就視為有副作用 (檔案寫入、shell、網路 POST...)，執行前一定要使用者確認。
+/// `confirm_all` 則是給想要「每個工具都問」的 power user 用的總開關。
+pub struct ApprovalGate {
+    session_approved: HashSet<String>,
+}
+
+impl ApprovalGate {
+    pub fn new() -> Self {
+        Self { session_approved: HashSet::new() }
+    }
+
+    fn needs_confirmation(tool_name: &str, confirm_all: bool) -> bool {
+        confirm_all || tool_name.starts_with("may_")
+    }
+
+    /// 回傳 true 代表可以執行；false 代表使用者拒絕了 (呼叫端應該把拒絕原因
+    /// 當成 FunctionResponse 的 error 塞回去，讓模型知道發生了什麼事)。
+    pub fn check(&mut self, resolved_name: &str, tool_name: &str, args: &Value, settings: &Settings) -> bool {
+        if !Self::needs_confirmation(tool_name, settings.tool_policy.confirm_all) {
+            return true;
+        }
+
+        if settings.tool_policy.auto_approve.iter().any(|n| n == resolved_name) {
+            return true;
+        }
+        if self.session_approved.contains(resolved_name) {
+            return true;
+        }
+
+        println!("{}", format!("⚠ 此工具可能有副作用: {}", resolved_name).yellow().bold());
+        println!("{}", serde_json::to_string_pretty(args).unwrap_or_else(|_| args.to_string()));
+        print!("Execute? [y/N/always]: ");
+        let _ = io::stdout().flush();
+
+        let mut input = String::new();
+        if io::stdin().read_line(&mut input).is_err() {
+            return false;
+        }
+
+        match input.trim().to_lowercase().as_str() {
+            "y" | "yes" => true,
+            "always" => {
+                self.session_approved.insert(resolved_name.to_string());
+                true
+            }
+            _ => false,
+        }
+    }
+}
@@ -1,15 +1,19 @@
 use crate::config::Settings;
 use crate::chat::session::ChatSession;
-use crate::codegemini::store::{SimpleVectorStore, VectorDocument};
+use crate::codegemini::store::{SimpleVectorStore, VectorDocument, SearchMode};
 use crate::codegemini::embeddings::EmbeddingGenerator;
 use crate::codegemini::walker::FileWalker;
 use crate::codegemini::chunker::Chunker;
 use crate::mcp::client::McpClient;
 use crate::client::imagen::ImagenClient;
+use crate::client::auth::GoogleAuth;
+use crate::client::cache::CacheManager;
+use crate::client::storage::StorageTarget;
 use crate::chat::doctor::Doctor;
 use colored::Colorize;
 use std::collections::HashMap;
 use std::path::Path;
+use std::sync::Arc;
 use rust_i18n::t;
 
 pub struct AppState<'a> {
@@ -19,6 +23,11 @@ pub struct AppState<'a> {
     pub embedding_generator: &'a EmbeddingGenerator<'a>,
     pub mcp_clients: &'a mut HashMap<String, McpClient>,
     pub imagen_client: &'a ImagenClient<'a>,
+    pub cache_manager: &'a CacheManager<'a>,
+    pub active_cache_name: &'a mut Option<String>,
+    // 設定了就把生成圖片/向量索引改存到這個 `gs://bucket/prefix`，None 就維持本地。
+    pub storage_target: Option<&'a StorageTarget>,
+    pub auth: Arc<GoogleAuth>,
 }
 
 pub async fn handle_command(line: &str, state: &mut AppState<'_>) -> bool {
@@ -38,6 +47,7 @@ pub async fn handle_command(line: &str, state: &mut AppState<'_>) -> bool {
             println!("  /image <p>   - {}", t!("command_image_desc"));
             println!("  /doctor      - System diagnostics");
             println!("  /mcp ...     - {}", t!("command_mcp_desc"));
+            println!("  /session ... - save|load|list|new named chat sessions");
             println!("  /exit        - {}", t!("command_exit_desc"));
         }
         "/exit" => {
@@ -87,12 +97,26 @@ pub async fn handle_command(line: &str, state: &mut AppState<'_>) -> bool {
                                 let _ = std::io::stdout().flush();
                             }
                             println!("\nIndexing complete. {} chunks stored.", state.vector_store.count());
-                            
-                            // Save to disk
-                            if let Err(e) = state.vector_store.save("codegemini_index.json") {
-                                eprintln!("Failed to save index: {}", e);
-                            } else {
-                                println!("Index saved to codegemini_index.json");
+
+                            // 依設定存到本地或 `storage_target` 指定的 `gs://bucket/prefix`。
+                            match state.storage_target {
+                                Some(target) => {
+                                    match state.vector_store.save_to(target, "codegemini_index.json", state.auth.clone()).await {
+                                        Ok(Some(uploaded)) => println!("Index uploaded to GCS: {:?}", uploaded.media_link),
+                                        Ok(None) => match target {
+                                            StorageTarget::Local(dir) => println!("Index saved to {}", Path::new(dir).join("codegemini_index.json").display()),
+                                            StorageTarget::Gcs { .. } => println!("Index saved to codegemini_index.json"),
+                                        },
+                                        Err(e) => eprintln!("Failed to save index: {}", e),
+                                    }
+                                }
+                                None => {
+                                    if let Err(e) = state.vector_store.save("codegemini_index.json") {
+                                        eprintln!("Failed to save index: {}", e);
+                                    } else {
+                                        println!("Index saved to codegemini_index.json");
+                                    }
+                                }
                             }
                         },
                         Err(e) => eprintln!("Walk failed: {}", e),
@@ -103,14 +127,24 @@ pub async fn handle_command(line: &str, state: &mut AppState<'_>) -> bool {
             }
         }
         "/search" => {
-            let query = args.join(" ");
+            let mode = if args.iter().any(|a| *a == "--lexical") {
+                SearchMode::Lexical
+            } else if args.iter().any(|a| *a == "--semantic") {
+                SearchMode::Semantic
+            } else {
+                SearchMode::Hybrid
+            };
+            let query: Vec<&&str> = args.iter()
+                .filter(|a| **a != "--lexical" && **a != "--semantic")
+                .collect();
+            let query = query.into_iter().copied().collect::<Vec<&str>>().join(" ");
             if query.is_empty() {
-                println!("Usage: /search <query>");
+                println!("Usage: /search <query> [--lexical|--semantic]");
             } else {
                 println!("Searching for '{}'...", query);
                 match state.embedding_generator.generate_embedding(&query).await {
                     Ok(vec) => {
-                        let results = state.vector_store.search(&vec, 3);
+                        let results = state.vector_store.search_hybrid(&vec, &query, 3, mode);
                         for (doc, score) in results {
                             println!("--- Score: {:.4} ---", score);
                             println!("File: {}", doc.file_path.cyan());
@@ -122,14 +156,38 @@ pub async fn handle_command(line: &str, state: &mut AppState<'_>) -> bool {
             }
         }
         "/image" => {
-            let prompt = args.join(" ");
+            let no_preview = args.iter().any(|a| *a == "--no-preview");
+            let share_mastodon = args.iter().any(|a| *a == "--share-mastodon");
+            let prompt_args: Vec<&str> = args.iter()
+                .filter(|a| **a != "--no-preview" && **a != "--share-mastodon")
+                .copied()
+                .collect();
+            let prompt = prompt_args.join(" ");
             if prompt.is_empty() {
-                println!("{}", "Usage: /image <prompt>".red());
+                println!("{}", "Usage: /image <prompt> [--no-preview] [--share-mastodon]".red());
             } else {
                 println!("{}", t!("image_generating", prompt = prompt).blue());
-                match state.imagen_client.generate_image(&prompt).await {
-                    Ok(path) => {
+                // 依設定存到本地或 `storage_target` 指定的 `gs://bucket/prefix`。
+                let result = match state.storage_target {
+                    Some(target) => state.imagen_client.generate_image_to(&prompt, target).await
+                        .map(|img| (img.local_path, img.media_link)),
+                    None => state.imagen_client.generate_image(&prompt).await.map(|path| (path, None)),
+                };
+                match result {
+                    Ok((path, media_link)) => {
+                         if !no_preview {
+                             crate::client::imagen::preview_image(&path);
+                         }
                          println!("{} {}", "✓".green(), t!("image_saved", path = path.display()));
+                         if let Some(link) = media_link {
+                             println!("{} {}", "✓".green(), format!("已上傳到 GCS: {}", link));
+                         }
+                         if share_mastodon {
+                             match crate::client::mastodon::share_to_mastodon(state.settings, &path, &prompt).await {
+                                 Ok(url) => println!("{} {}", "✓".green(), format!("已發布到 Mastodon: {}", url)),
+                                 Err(e) => println!("{} {}", "✗".red(), format!("發布到 Mastodon 失敗: {}", e)),
+                             }
+                         }
                     },
                     Err(e) => {
                          println!("{} {}", "✗".red(), format!("Image generation failed: {}", e));
@@ -199,6 +257,71 @@ pub async fn handle_command(line: &str, state: &mut AppState<'_>) -> bool {
                 }
             }
         }
+        "/session" => {
+            let subcmd = args.first().copied().unwrap_or("");
+            match subcmd {
+                "save" => {
+                    let name = args.get(1).copied()
+                        .or(state.session.name.as_deref())
+                        .map(|s| s.to_string());
+                    match name {
+                        Some(name) => {
+                            state.session.name = Some(name.clone());
+                            state.session.active_cache_name = state.active_cache_name.clone();
+                            match state.session.save(&name) {
+                                Ok(_) => println!("{} Session 已存成 '{}'", "✓".green(), name),
+                                Err(e) => eprintln!("儲存 session 失敗: {}", e),
+                            }
+                        }
+                        None => println!("Usage: /session save <name>"),
+                    }
+                }
+                "load" => {
+                    if let Some(name) = args.get(1) {
+                        match ChatSession::load(name) {
+                            Ok(loaded) => {
+                                // 存檔裡記的 cache 可能早就過期/被刪了，要先問一次 API 確認還活著，
+                                // 不然下一個請求會帶著一個死掉的 cached_content 名稱去打 generateContent。
+                                *state.active_cache_name = match &loaded.active_cache_name {
+                                    Some(cache_name) => match state.cache_manager.get(cache_name).await {
+                                        Ok(_) => Some(cache_name.clone()),
+                                        Err(_) => None,
+                                    },
+                                    None => None,
+                                };
+                                *state.session = loaded;
+                                println!("{} 已載入 session '{}' ({} 則訊息)", "✓".green(), name, state.session.history.len());
+                            }
+                            Err(e) => eprintln!("載入 session 失敗: {}", e),
+                        }
+                    } else {
+                        println!("Usage: /session load <name>");
+                    }
+                }
+                "list" => {
+                    match ChatSession::list() {
+                        Ok(names) => {
+                            if names.is_empty() {
+                                println!("(沒有已儲存的 session)");
+                            } else {
+                                for name in names {
+                                    println!("- {}", name);
+                                }
+                            }
+                        }
+                        Err(e) => eprintln!("讀取 session 列表失敗: {}", e),
+                    }
+                }
+                "new" => {
+                    let name = args.get(1).map(|s| s.to_string());
+                    *state.session = ChatSession::new();
+                    state.session.name = name;
+                    *state.active_cache_name = None;
+                    println!("{}", "已開啟新的 session".green());
+                }
+                _ => println!("Usage: /session save|load|list|new [name]"),
+            }
+        }
         _ => {
             println!("Unknown command: {}", command.red());
         }
@@ -41,13 +41,44 @@ impl PricingCalculator {
 
     pub fn calculate(&self, model: &str, input_tokens: u32, output_tokens: u32) -> (f64, f64) {
         let pricing = self.get_model_pricing(model);
-        
+
         let input_cost_usd = (input_tokens as f64 / 1_000_000.0) * pricing.input_price_per_1m;
         let output_cost_usd = (output_tokens as f64 / 1_000_000.0) * pricing.output_price_per_1m;
-        
+
         let total_usd = input_cost_usd + output_cost_usd;
         let total_twd = total_usd * self.usd_to_twd;
-        
+
+        (total_usd, total_twd)
+    }
+
+    // Vertex AI 依 GCP 地區收費，部分地區較公開 API 貴一些。
+    // 這裡用一個粗略的地區加成係數，而不是完整的 Vertex 費率表。
+    fn vertex_region_multiplier(region: &str) -> f64 {
+        match region {
+            "us-central1" | "us-east1" | "us-east4" | "us-west1" | "europe-west1" | "europe-west4" => 1.0,
+            "asia-northeast1" | "asia-southeast1" | "asia-south1" => 1.1,
+            _ => 1.15,
+        }
+    }
+
+    pub fn get_model_pricing_vertex(&self, model_name: &str, region: &str) -> PricingModel {
+        let base = self.get_model_pricing(model_name);
+        let multiplier = Self::vertex_region_multiplier(region);
+        PricingModel {
+            input_price_per_1m: base.input_price_per_1m * multiplier,
+            output_price_per_1m: base.output_price_per_1m * multiplier,
+        }
+    }
+
+    pub fn calculate_vertex(&self, model: &str, region: &str, input_tokens: u32, output_tokens: u32) -> (f64, f64) {
+        let pricing = self.get_model_pricing_vertex(model, region);
+
+        let input_cost_usd = (input_tokens as f64 / 1_000_000.0) * pricing.input_price_per_1m;
+        let output_cost_usd = (output_tokens as f64 / 1_000_000.0) * pricing.output_price_per_1m;
+
+        let total_usd = input_cost_usd + output_cost_usd;
+        let total_twd = total_usd * self.usd_to_twd;
+
         (total_usd, total_twd)
     }
 }
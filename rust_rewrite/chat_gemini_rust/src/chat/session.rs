@@ -1,6 +1,10 @@
 use crate::client::models::{Content, Part};
+use anyhow::{Context, Result};
 use chrono::{DateTime, Local};
 use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatMessage {
@@ -9,10 +13,19 @@ pub struct ChatMessage {
     pub timestamp: DateTime<Local>,
 }
 
+// 所有具名的 session 都存在這個目錄底下，一個 session 一個 .json 檔。
+const SESSIONS_DIR: &str = "sessions";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatSession {
     pub history: Vec<ChatMessage>,
     pub total_cost: f64,
+    // 目前這個 session 的名字；`/session save` 不帶參數時會沿用這個名字儲存。
+    #[serde(default)]
+    pub name: Option<String>,
+    // 存檔當下綁定的 context cache 名稱，`/session load` 後會還原成當時的快取狀態。
+    #[serde(default)]
+    pub active_cache_name: Option<String>,
 }
 
 impl ChatSession {
@@ -20,7 +33,49 @@ impl ChatSession {
         Self {
             history: Vec::new(),
             total_cost: 0.0,
+            name: None,
+            active_cache_name: None,
+        }
+    }
+
+    fn path_for(name: &str) -> PathBuf {
+        Path::new(SESSIONS_DIR).join(format!("{}.json", name))
+    }
+
+    pub fn save(&self, name: &str) -> Result<()> {
+        fs::create_dir_all(SESSIONS_DIR)?;
+        let path = Self::path_for(name);
+        let file = File::create(&path).with_context(|| format!("無法寫入 session 檔案: {}", path.display()))?;
+        let writer = BufWriter::new(file);
+        serde_json::to_writer_pretty(writer, self).context("Session 序列化失敗")?;
+        Ok(())
+    }
+
+    pub fn load(name: &str) -> Result<Self> {
+        let path = Self::path_for(name);
+        let file = File::open(&path).with_context(|| format!("找不到 session: {}", name))?;
+        let reader = BufReader::new(file);
+        let session = serde_json::from_reader(reader).context("Session 檔案格式錯誤")?;
+        Ok(session)
+    }
+
+    pub fn list() -> Result<Vec<String>> {
+        let dir = Path::new(SESSIONS_DIR);
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut names = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) == Some("json") {
+                if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
+                    names.push(stem.to_string());
+                }
+            }
         }
+        names.sort();
+        Ok(names)
     }
 
     pub fn add_user_message(&mut self, text: &str) {
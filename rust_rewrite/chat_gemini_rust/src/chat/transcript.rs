@@ -0,0 +1,34 @@
+use serde::Serialize;
+use std::fs::OpenOptions;
+use std::io::Write;
+
+/// 把 agent loop 每一步的事件寫成 JSONL，方便事後重播/除錯。`log_file` 設定
+/// 留空就完全不動作，不會多開檔案也不影響既有行為。
+pub struct TranscriptLogger {
+    path: Option<String>,
+}
+
+impl TranscriptLogger {
+    pub fn new(path: Option<String>) -> Self {
+        Self { path }
+    }
+
+    pub fn log(&self, event: &serde_json::Value) {
+        let Some(path) = &self.path else { return };
+        if let Err(e) = Self::append(path, event) {
+            eprintln!("寫入 transcript 失敗: {}", e);
+        }
+    }
+
+    fn append(path: &str, event: &serde_json::Value) -> std::io::Result<()> {
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        writeln!(file, "{}", event)
+    }
+}
+
+pub fn event<S: Serialize>(kind: &str, payload: S) -> serde_json::Value {
+    serde_json::json!({
+        "event": kind,
+        "data": payload,
+    })
+}
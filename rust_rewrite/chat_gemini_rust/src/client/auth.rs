@@ -7,26 +7,55 @@ use anyhow::{Result, Context};
 use std::sync::Arc;
 use colored::Colorize;
 use crate::config::Settings;
+use crate::client::pkce::{self, SecretString};
+use tokio::sync::RwLock;
+use serde::{Serialize, Deserialize};
 
 // Scope for Gemini API
 const SCOPE: &str = "https://www.googleapis.com/auth/generativelanguage";
 const CLOUD_PLATFORM_SCOPE: &str = "https://www.googleapis.com/auth/cloud-platform";
 
-#[derive(Clone)]
+// PKCE 流程換到的 refresh token 持久化路徑，跟 yup_oauth2 的 `token_cache.json` 同一層級。
+const PKCE_TOKEN_FILE: &str = "pkce_refresh_token.json";
+
+#[derive(Serialize, Deserialize)]
+struct PersistedPkceToken {
+    client_id: String,
+    refresh_token: String,
+}
+
 pub enum AuthMethod {
     ApiKey(String),
     OAuth(Arc<Authenticator<HttpsConnector<HttpConnector>>>),
+    // 手動實作的 PKCE loopback 流程；跟上面的 `OAuth` 不同，不需要 client_secret，
+    // 只靠 client_id + code_verifier 就能完成授權，適合純前端/原生應用的情境。
+    Pkce {
+        client_id: String,
+        refresh_token: SecretString,
+        cached_access_token: RwLock<Option<(SecretString, u64)>>,
+    },
 }
 
 pub struct GoogleAuth {
     method: AuthMethod,
+    service_account_email: Option<String>,
+}
+
+// 從 ADC 慣用的服務帳戶金鑰檔案 (GOOGLE_APPLICATION_CREDENTIALS 指到的 JSON) 嘗試讀出
+// `client_email`，GCS 簽署下載網址需要知道是哪個服務帳戶在簽。讀不到就回傳 None，
+// 呼叫端需要自行處理「沒有服務帳戶可簽」的情況。
+fn read_service_account_email() -> Option<String> {
+    let path = std::env::var("GOOGLE_APPLICATION_CREDENTIALS").ok()?;
+    let content = std::fs::read_to_string(path).ok()?;
+    let json: serde_json::Value = serde_json::from_str(&content).ok()?;
+    json.get("client_email")?.as_str().map(String::from)
 }
 
 impl GoogleAuth {
     pub async fn new(settings: &Settings) -> Result<Self> {
         // 1. API Key Priority
         if !settings.gemini_api_key.is_empty() {
-            return Ok(Self { method: AuthMethod::ApiKey(settings.gemini_api_key.clone()) });
+            return Ok(Self { method: AuthMethod::ApiKey(settings.gemini_api_key.clone()), service_account_email: None });
         }
 
         println!("{}", "正在嘗試 Application Default Credentials (ADC)...".yellow());
@@ -42,7 +71,18 @@ impl GoogleAuth {
 
         if let Ok(auth) = adc_result {
              println!("{}", "已連接 ADC 憑證。".green());
-             return Ok(Self { method: AuthMethod::OAuth(Arc::new(auth)) });
+             return Ok(Self { method: AuthMethod::OAuth(Arc::new(auth)), service_account_email: read_service_account_email() });
+        }
+
+        // 2.5 使用者在設定裡填了 PKCE client_id：優先復用上次登入留下的 refresh token，
+        // 沒有快取才跑一次互動登入 (不需要 client_secret.json，適合純前端/原生應用情境)。
+        if let Some(client_id) = &settings.oauth_pkce_client_id {
+            if let Ok(cached) = Self::from_pkce_cache() {
+                println!("{}", "已復用先前 PKCE 登入留下的 refresh token。".green());
+                return Ok(cached);
+            }
+            println!("{}", "未找到快取的 PKCE refresh token，啟動互動登入流程...".blue());
+            return Self::login_pkce(client_id).await;
         }
 
         // 3. Try Interactive Flow (OAuth 2.0 Client ID)
@@ -76,7 +116,46 @@ impl GoogleAuth {
         .await?;
 
         println!("{}", "OAuth 初始化完成。請在瀏覽器中完成登入。".green());
-        Ok(Self { method: AuthMethod::OAuth(Arc::new(auth)) })
+        Ok(Self { method: AuthMethod::OAuth(Arc::new(auth)), service_account_email: None })
+    }
+
+    // 走 PKCE loopback 流程登入：第一次需要互動 (跳出同意網址讓使用者開瀏覽器)，
+    // 換到的 refresh token 會存到 `pkce_refresh_token.json`，之後可以直接用
+    // [`Self::from_pkce_cache`] 免互動復用。
+    pub async fn login_pkce(client_id: &str) -> Result<Self> {
+        let tokens = pkce::interactive_login(client_id, &[SCOPE, CLOUD_PLATFORM_SCOPE]).await?;
+
+        let persisted = PersistedPkceToken {
+            client_id: client_id.to_string(),
+            refresh_token: tokens.refresh_token.expose().to_string(),
+        };
+        std::fs::write(PKCE_TOKEN_FILE, serde_json::to_string_pretty(&persisted)?)
+            .context("寫入 pkce_refresh_token.json 失敗")?;
+
+        Ok(Self {
+            method: AuthMethod::Pkce {
+                client_id: client_id.to_string(),
+                refresh_token: tokens.refresh_token,
+                cached_access_token: RwLock::new(Some((tokens.access_token, tokens.expires_at))),
+            },
+            service_account_email: None,
+        })
+    }
+
+    /// 從上次 [`Self::login_pkce`] 留下的 `pkce_refresh_token.json` 復原，不需要再跑一次互動登入。
+    pub fn from_pkce_cache() -> Result<Self> {
+        let content = std::fs::read_to_string(PKCE_TOKEN_FILE)
+            .context("找不到 pkce_refresh_token.json，請先用 login_pkce 登入一次")?;
+        let persisted: PersistedPkceToken = serde_json::from_str(&content)?;
+
+        Ok(Self {
+            method: AuthMethod::Pkce {
+                client_id: persisted.client_id,
+                refresh_token: SecretString::new(persisted.refresh_token),
+                cached_access_token: RwLock::new(None),
+            },
+            service_account_email: None,
+        })
     }
 
     pub async fn get_token(&self) -> Result<Option<String>> {
@@ -84,22 +163,45 @@ impl GoogleAuth {
             AuthMethod::ApiKey(_) => Ok(None),
             AuthMethod::OAuth(auth) => {
                 let token = auth.token(&[SCOPE]).await;
-                
+
                 let token = match token {
                     Ok(t) => t,
                     Err(_) => auth.token(&[CLOUD_PLATFORM_SCOPE]).await.context("無法獲取 OAuth Token")?,
                 };
-                
+
                 let token_str = token.token().ok_or_else(|| anyhow::anyhow!("Empty token"))?;
                 Ok(Some(token_str.to_string()))
             }
+            AuthMethod::Pkce { client_id, refresh_token, cached_access_token } => {
+                // 留 60 秒緩衝，快過期就提早刷新，避免卡在邊界剛好送出一個馬上失效的 token。
+                const EXPIRY_SKEW_SECS: u64 = 60;
+                let now = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap()
+                    .as_secs();
+
+                if let Some((token, expires_at)) = cached_access_token.read().await.as_ref() {
+                    if now + EXPIRY_SKEW_SECS < *expires_at {
+                        return Ok(Some(token.expose().to_string()));
+                    }
+                }
+
+                let (fresh_token, expires_at) = pkce::refresh_access_token(client_id, refresh_token).await?;
+                let token_str = fresh_token.expose().to_string();
+                *cached_access_token.write().await = Some((fresh_token, expires_at));
+                Ok(Some(token_str))
+            }
         }
     }
-    
+
     pub fn get_api_key(&self) -> Option<&str> {
         match &self.method {
             AuthMethod::ApiKey(key) => Some(key),
-            AuthMethod::OAuth(_) => None,
+            AuthMethod::OAuth(_) | AuthMethod::Pkce { .. } => None,
         }
     }
+
+    pub fn service_account_email(&self) -> Option<&str> {
+        self.service_account_email.as_deref()
+    }
 }
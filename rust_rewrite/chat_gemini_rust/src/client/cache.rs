@@ -2,25 +2,65 @@ use crate::client::rest::GeminiClient;
 use crate::client::models::CachedContent;
 use anyhow::Result;
 use reqwest::Response;
+use serde::Deserialize;
+use serde_json::json;
 
 pub struct CacheManager<'a> {
     client: &'a GeminiClient,
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListCachedContentsResponse {
+    #[serde(default)]
+    cached_contents: Vec<CachedContent>,
+}
+
 impl<'a> CacheManager<'a> {
     pub fn new(client: &'a GeminiClient) -> Self {
         Self { client }
     }
 
     pub async fn create(&self, cached_content: &CachedContent) -> Result<CachedContent> {
-        let url = format!("{}/cachedContents", self.client.base_url);
-        
-        let res: Response = self.client.post(&url).await
-            .json(cached_content)
-            .send()
-            .await?;
-
-        let response: CachedContent = res.error_for_status()?.json().await?;
+        let url = format!("{}/cachedContents", self.client.resource_base());
+
+        let req = self.client.post(&url).await.json(cached_content);
+        let res: Response = self.client.send_with_retry(req).await?;
+
+        let response: CachedContent = res.json().await?;
+        Ok(response)
+    }
+
+    // `name` 是 create() 回傳的 "cachedContents/xxx" 格式，其餘方法同理。
+    pub async fn get(&self, name: &str) -> Result<CachedContent> {
+        let url = format!("{}/{}", self.client.resource_base(), name);
+        let req = self.client.get(&url).await;
+        let res: Response = self.client.send_with_retry(req).await?;
+        let response: CachedContent = res.json().await?;
         Ok(response)
     }
+
+    pub async fn list(&self) -> Result<Vec<CachedContent>> {
+        let url = format!("{}/cachedContents", self.client.resource_base());
+        let req = self.client.get(&url).await;
+        let res: Response = self.client.send_with_retry(req).await?;
+        let response: ListCachedContentsResponse = res.json().await?;
+        Ok(response.cached_contents)
+    }
+
+    // 只更新 TTL，用 PATCH + update_mask，不用整包 CachedContent 重送。
+    pub async fn update_ttl(&self, name: &str, ttl: &str) -> Result<CachedContent> {
+        let url = format!("{}/{}?updateMask=ttl", self.client.resource_base(), name);
+        let req = self.client.patch(&url).await.json(&json!({ "ttl": ttl }));
+        let res: Response = self.client.send_with_retry(req).await?;
+        let response: CachedContent = res.json().await?;
+        Ok(response)
+    }
+
+    pub async fn delete(&self, name: &str) -> Result<()> {
+        let url = format!("{}/{}", self.client.resource_base(), name);
+        let req = self.client.delete(&url).await;
+        self.client.send_with_retry(req).await?;
+        Ok(())
+    }
 }
@@ -4,8 +4,16 @@ use anyhow::Result;
 use std::path::Path;
 use tokio::fs::File;
 use tokio::io::AsyncReadExt;
+use tokio::sync::mpsc;
 use reqwest::{Body, Response};
 
+// 8 MiB，必須是 256 KiB 的倍數 (resumable upload protocol 的要求)
+const CHUNK_SIZE: usize = 8 * 1024 * 1024;
+// 讀取執行緒最多可以領先上傳幾塊，避免在快速磁碟/慢速連線上無止盡地堆積記憶體
+const MAX_READ_AHEAD: usize = 4;
+
+pub type UploadProgressCallback = Box<dyn Fn(u64, u64) + Send + Sync>;
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct FileData {
@@ -36,6 +44,57 @@ struct UploadResponse {
     file: FileData,
 }
 
+struct UploadChunk {
+    offset: u64,
+    data: Vec<u8>,
+    is_last: bool,
+}
+
+// 背景讀檔：依序把檔案切成固定大小的區塊送進 channel，讓上傳端可以邊等網路
+// 回應邊收下一塊。`size` 是 Step 1 當下量到的檔案大小；如果還沒讀滿 `size`
+// 就先碰到 EOF (檔案被其他程序截斷、或一開始量到的大小就有誤)，直接回傳錯誤，
+// 不能像舊版那樣放任 offset 停滯不前、永遠送出 0 bytes 的 "upload" 指令卡死。
+async fn read_chunks(mut file: File, size: u64, tx: mpsc::Sender<Result<UploadChunk>>) {
+    let mut offset: u64 = 0;
+    loop {
+        let remaining = (size - offset).min(CHUNK_SIZE as u64) as usize;
+        let mut buffer = vec![0u8; remaining];
+        let mut filled = 0usize;
+        let mut hit_eof = false;
+
+        while filled < remaining {
+            match file.read(&mut buffer[filled..remaining]).await {
+                Ok(0) => { hit_eof = true; break; }
+                Ok(n) => filled += n,
+                Err(e) => {
+                    let _ = tx.send(Err(e.into())).await;
+                    return;
+                }
+            }
+        }
+
+        let new_offset = offset + filled as u64;
+        if hit_eof && new_offset < size {
+            let _ = tx.send(Err(anyhow::anyhow!(
+                "讀取檔案時提早遇到 EOF：預期共 {} bytes，實際只讀到 {} bytes",
+                size, new_offset
+            ))).await;
+            return;
+        }
+
+        buffer.truncate(filled);
+        let is_last = new_offset >= size;
+        if tx.send(Ok(UploadChunk { offset, data: buffer, is_last })).await.is_err() {
+            return; // 上傳端已經放棄 (例如某一塊上傳失敗提早回傳)，沒必要繼續讀
+        }
+
+        offset = new_offset;
+        if is_last {
+            return;
+        }
+    }
+}
+
 pub struct FileManager<'a> {
     client: &'a GeminiClient,
 }
@@ -46,53 +105,103 @@ impl<'a> FileManager<'a> {
     }
 
     pub async fn upload(&self, path: &Path, mime_type: &str) -> Result<FileData> {
-        let upload_url_base = "https://generativelanguage.googleapis.com/upload/v1beta/files";
-        
+        self.upload_with_progress(path, mime_type, None).await
+    }
+
+    pub async fn upload_with_progress(
+        &self,
+        path: &Path,
+        mime_type: &str,
+        on_progress: Option<UploadProgressCallback>,
+    ) -> Result<FileData> {
+        // Vertex 沒有對等的 Files API，維持原本的 Generative Language 上傳端點即可，
+        // 僅在走 Vertex 後端時改用其資源前綴下的 /files:upload，供企業內部代理情境使用。
+        let upload_url_base = match &self.client.backend {
+            super::rest::Backend::GenerativeLanguage => "https://generativelanguage.googleapis.com/upload/v1beta/files".to_string(),
+            super::rest::Backend::Vertex { .. } => format!("{}/files:upload", self.client.resource_base()),
+        };
+        let upload_url_base = upload_url_base.as_str();
+
         // Use client wrapper but note upload URL is different base (upload/v1beta vs v1beta)
         // Client wrapper prepends auth logic.
         // We need to use `client.post` with the full URL.
-        
+
         let file_name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
         let metadata = UploadMetadata {
             file: FileMetadata { display_name: file_name },
         };
-        
+
         let mut file = File::open(path).await?;
         let size = file.metadata().await?.len();
-        
+
         // Step 1: Start Upload Session
-        let res: Response = self.client.post(upload_url_base).await
+        let req = self.client.post(upload_url_base).await
             .header("X-Goog-Upload-Protocol", "resumable")
             .header("X-Goog-Upload-Command", "start")
             .header("X-Goog-Upload-Header-Content-Length", size.to_string())
             .header("X-Goog-Upload-Header-Content-Type", mime_type)
-            .json(&metadata)
-            .send()
-            .await?;
-            
-        let res = res.error_for_status()?;
+            .json(&metadata);
+        let res: Response = self.client.send_with_retry(req).await?;
+
         let upload_url = res.headers().get("x-goog-upload-url")
             .ok_or_else(|| anyhow::anyhow!("No upload URL returned"))?
-            .to_str()?;
-
-        // Step 2: Upload Bytes
-        let mut buffer = Vec::new();
-        file.read_to_end(&mut buffer).await?;
-        
-        // Step 2 uses PUT to a specific upload_url. Auth headers are usually NOT required for the session URL,
-        // but let's check. Google Upload Protocol usually embeds token in the upload_url or session.
-        // If we add auth header again it might be fine or redundant.
-        // Let's use `client.put` which ADDS auth header. If it fails, we revert to raw client.
-        
-        let res: Response = self.client.put(upload_url).await
-            .header("Content-Length", size.to_string())
-            .header("X-Goog-Upload-Offset", "0")
-            .header("X-Goog-Upload-Command", "upload, finalize")
-            .body(Body::from(buffer))
-            .send()
-            .await?;
-            
-        let response: UploadResponse = res.error_for_status()?.json().await?;
-        Ok(response.file)
+            .to_str()?
+            .to_string();
+
+        // Step 2: 以固定大小的區塊串流上傳，避免像舊版一樣把整個檔案讀進記憶體
+        // (多 GB 的影音檔會直接 OOM)。讀檔跟上傳分別跑在獨立的 task 上，
+        // 靠一個有容量上限的 channel 串起來：讀檔 task 可以領先上傳最多
+        // `MAX_READ_AHEAD` 塊，讀磁碟跟打網路請求因此能真正重疊進行；
+        // 但 channel 保證先進先出，實際送出的 PUT 仍然依 offset 嚴格依序發出，
+        // 符合 resumable upload protocol 的要求 (伺服器依 offset 驗證接續性)。
+        let (tx, mut rx) = mpsc::channel::<Result<UploadChunk>>(MAX_READ_AHEAD);
+        tokio::spawn(read_chunks(file, size, tx));
+
+        let mut offset: u64 = 0;
+        while let Some(chunk) = rx.recv().await {
+            let chunk = chunk?;
+            let command = if chunk.is_last { "upload, finalize" } else { "upload" };
+            let chunk_end = chunk.offset + chunk.data.len() as u64;
+
+            let req = self.client.put(&upload_url).await
+                .header("Content-Length", chunk.data.len().to_string())
+                .header("X-Goog-Upload-Offset", chunk.offset.to_string())
+                .header("X-Goog-Upload-Command", command)
+                .body(Body::from(chunk.data));
+            let res: Response = self.client.send_with_retry(req).await?;
+
+            offset = chunk_end;
+            if let Some(cb) = &on_progress {
+                cb(offset, size);
+            }
+
+            if chunk.is_last {
+                let response: UploadResponse = res.json().await?;
+                return Ok(response.file);
+            }
+        }
+
+        Err(anyhow::anyhow!("上傳在第 {} bytes 處中斷：讀檔 task 提早結束", offset))
+    }
+
+    // 剛上傳完的影片/音訊常常還卡在 PROCESSING (伺服器要抽幀、轉寫字幕等後製)，
+    // 得等狀態變成 ACTIVE 才能被 generateContent 透過 file_uri 引用。
+    pub async fn get_file(&self, name: &str) -> Result<FileData> {
+        let url = format!("{}/{}", self.client.resource_base(), name);
+        let req = self.client.get(&url).await;
+        let res: Response = self.client.send_with_retry(req).await?;
+        Ok(res.json().await?)
+    }
+
+    /// 輪詢 `name` 直到狀態變成 ACTIVE (回傳) 或 FAILED (回傳錯誤)，兩次查詢間隔固定兩秒。
+    pub async fn wait_until_active(&self, name: &str) -> Result<FileData> {
+        loop {
+            let file = self.get_file(name).await?;
+            match file.state.as_str() {
+                "ACTIVE" => return Ok(file),
+                "FAILED" => return Err(anyhow::anyhow!("檔案處理失敗：{}", name)),
+                _ => tokio::time::sleep(std::time::Duration::from_secs(2)).await,
+            }
+        }
     }
 }
@@ -1,12 +1,21 @@
 use crate::client::rest::GeminiClient;
+use crate::client::storage::{StorageTarget, GcsUploader};
 use serde::{Deserialize, Serialize};
 use anyhow::Result;
 use reqwest::Response;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tokio::fs;
 use base64::prelude::*;
 use chrono::Local;
 
+/// `generate_image_to` 的結果：本地檔案一定有，若存到 GCS 則額外帶回
+/// media link 與（能簽章時的）有時效下載網址。
+pub struct GeneratedImage {
+    pub local_path: PathBuf,
+    pub media_link: Option<String>,
+    pub signed_url: Option<String>,
+}
+
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 struct ImagenRequest {
@@ -50,7 +59,43 @@ impl<'a> ImagenClient<'a> {
     }
 
     pub async fn generate_image(&self, prompt: &str) -> Result<PathBuf> {
-        let url = format!("{}/models/imagen-3.0-generate-001:predict", self.client.base_url);
+        self.generate_image_with_options(prompt, false).await
+    }
+
+    pub async fn generate_image_with_options(&self, prompt: &str, preview: bool) -> Result<PathBuf> {
+        let path = self.generate_image_inner(prompt).await?;
+        if preview {
+            preview_image(&path);
+        }
+        Ok(path)
+    }
+
+    /// 產生圖片並存到指定的 `StorageTarget`（本地目錄或 `gs://bucket/prefix`）。
+    /// 無論存去哪裡都會先落地到 `generated_images/`，再視目標決定要不要額外上傳到 GCS。
+    pub async fn generate_image_to(&self, prompt: &str, target: &StorageTarget) -> Result<GeneratedImage> {
+        let path = self.generate_image_inner(prompt).await?;
+
+        match target {
+            StorageTarget::Local(dir) => {
+                let dest = PathBuf::from(dir).join(path.file_name().unwrap_or_default());
+                if dest != path {
+                    fs::create_dir_all(dir).await?;
+                    fs::copy(&path, &dest).await?;
+                }
+                Ok(GeneratedImage { local_path: dest, media_link: None, signed_url: None })
+            }
+            StorageTarget::Gcs { .. } => {
+                let bytes = fs::read(&path).await?;
+                let filename = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+                let uploader = GcsUploader::new(self.client.auth.clone());
+                let uploaded = uploader.upload(target, &filename, bytes, "image/png").await?;
+                Ok(GeneratedImage { local_path: path, media_link: uploaded.media_link, signed_url: uploaded.signed_url })
+            }
+        }
+    }
+
+    async fn generate_image_inner(&self, prompt: &str) -> Result<PathBuf> {
+        let url = self.client.model_endpoint("imagen-3.0-generate-001", "predict", false);
 
         let request = ImagenRequest {
             instances: vec![ImagenInstance { prompt: prompt.to_string() }],
@@ -60,12 +105,10 @@ impl<'a> ImagenClient<'a> {
             },
         };
 
-        let res: Response = self.client.post(&url).await
-            .json(&request)
-            .send()
-            .await?;
+        let req = self.client.post(&url).await.json(&request);
+        let res: Response = self.client.send_with_retry(req).await?;
 
-        let response: ImagenResponse = res.error_for_status()?.json().await?;
+        let response: ImagenResponse = res.json().await?;
 
         if let Some(predictions) = response.predictions {
             if let Some(first) = predictions.first() {
@@ -86,4 +129,24 @@ impl<'a> ImagenClient<'a> {
 
         Err(anyhow::anyhow!("No image generated"))
     }
+}
+
+// 在終端機內顯示縮小後的圖片預覽 (適用於 SSH/headless 環境，省得再打開檔案總管)。
+// 終端機不支援圖形協定時，優雅地退回成只印出已儲存的路徑。
+pub fn preview_image(path: &Path) {
+    match image::open(path) {
+        Ok(img) => {
+            let conf = viuer::Config {
+                transparent: true,
+                absolute_offset: false,
+                ..Default::default()
+            };
+            if let Err(e) = viuer::print(&img, &conf) {
+                println!("(無法在此終端機預覽圖片，已儲存於: {}，原因: {})", path.display(), e);
+            }
+        }
+        Err(e) => {
+            println!("(讀取圖片以供預覽失敗: {}，已儲存於: {})", e, path.display());
+        }
+    }
 }
\ No newline at end of file
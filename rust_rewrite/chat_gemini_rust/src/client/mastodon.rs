@@ -0,0 +1,84 @@
+use crate::config::Settings;
+use anyhow::{Result, Context};
+use reqwest::{Client, multipart};
+use serde::Deserialize;
+use std::path::Path;
+
+#[derive(Debug, Deserialize)]
+struct MediaAttachment {
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Status {
+    url: Option<String>,
+}
+
+/// 走 megalodon 風格的純 REST 呼叫 (media 上傳 + 建立 status)，
+/// 不需要完整的 Mastodon SDK 就能把一張圖和文字發出去。
+pub struct MastodonClient {
+    client: Client,
+    instance_url: String,
+    access_token: String,
+}
+
+impl MastodonClient {
+    pub fn new(instance_url: impl Into<String>, access_token: impl Into<String>) -> Self {
+        Self {
+            client: Client::new(),
+            instance_url: instance_url.into().trim_end_matches('/').to_string(),
+            access_token: access_token.into(),
+        }
+    }
+
+    pub fn from_settings(settings: &Settings) -> Result<Self> {
+        let instance_url = settings.mastodon_instance_url.clone()
+            .context("未設定 mastodon_instance_url，請在 config 填入 Mastodon 站台網址")?;
+        let access_token = settings.mastodon_access_token.clone()
+            .context("未設定 mastodon_access_token，請在 config 填入存取權杖")?;
+        Ok(Self::new(instance_url, access_token))
+    }
+
+    async fn upload_media(&self, path: &Path) -> Result<String> {
+        let bytes = tokio::fs::read(path).await?;
+        let filename = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let part = multipart::Part::bytes(bytes).file_name(filename).mime_str("image/png")?;
+        let form = multipart::Form::new().part("file", part);
+
+        let url = format!("{}/api/v2/media", self.instance_url);
+        let res = self.client.post(&url)
+            .bearer_auth(&self.access_token)
+            .multipart(form)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        let media: MediaAttachment = res.json().await?;
+        Ok(media.id)
+    }
+
+    async fn post_status(&self, caption: &str, media_id: &str) -> Result<String> {
+        let url = format!("{}/api/v1/statuses", self.instance_url);
+        let res = self.client.post(&url)
+            .bearer_auth(&self.access_token)
+            .form(&[("status", caption), ("media_ids[]", media_id)])
+            .send()
+            .await?
+            .error_for_status()?;
+
+        let status: Status = res.json().await?;
+        status.url.context("Mastodon 回傳的 status 沒有 url")
+    }
+
+    /// 上傳一張圖片並附上文字發出去，回傳該 status 的公開網址。
+    pub async fn share_image(&self, path: &Path, caption: &str) -> Result<String> {
+        let media_id = self.upload_media(path).await?;
+        self.post_status(caption, &media_id).await
+    }
+}
+
+/// 一次性呼叫版本：從 `Settings` 讀站台/權杖，上傳 `path` 並發出帶 `caption` 的狀態。
+pub async fn share_to_mastodon(settings: &Settings, path: &Path, caption: &str) -> Result<String> {
+    let client = MastodonClient::from_settings(settings)?;
+    client.share_image(path, caption).await
+}
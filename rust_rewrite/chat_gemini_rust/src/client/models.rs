@@ -20,6 +20,10 @@ pub struct Part {
     pub function_call: Option<FunctionCall>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub function_response: Option<FunctionResponse>,
+    // 模型的「思考過程」也是用一般的 text part 送回來，靠這個欄位區分要不要
+    // 當成最終答案顯示給使用者。只有回應裡會出現，送出的 request 不會帶這個欄位。
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thought: Option<bool>,
 }
 
 impl Part {
@@ -30,6 +34,7 @@ impl Part {
             file_data: None,
             function_call: None,
             function_response: None,
+            thought: None,
         }
     }
 
@@ -40,6 +45,7 @@ impl Part {
             file_data: None,
             function_call: None,
             function_response: None,
+            thought: None,
         }
     }
 
@@ -50,6 +56,7 @@ impl Part {
             file_data: Some(FileDataPart { mime_type, file_uri }),
             function_call: None,
             function_response: None,
+            thought: None,
         }
     }
 }
@@ -100,11 +107,45 @@ pub struct FunctionDeclaration {
 #[serde(rename_all = "camelCase")]
 pub struct Schema {
     #[serde(rename = "type")]
-    pub schema_type: String, // OBJECT, STRING, etc.
+    pub schema_type: String, // OBJECT, STRING, ARRAY, ...
     #[serde(skip_serializing_if = "Option::is_none")]
     pub properties: Option<HashMap<String, Schema>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub required: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    // ARRAY 型別的元素 schema，遞迴套用同一套轉換
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub items: Option<Box<Schema>>,
+    // 字串/數字列舉值，Gemini 的 enum 只接受字串，數字會先轉成字串表示
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub r#enum: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub minimum: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub maximum: Option<f64>,
+    // JSON Schema 的 `"type": ["string", "null"]` (或 nullable: true) 轉過來的標記。
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nullable: Option<bool>,
+}
+
+impl Schema {
+    pub fn new(schema_type: impl Into<String>) -> Self {
+        Self {
+            schema_type: schema_type.into(),
+            properties: None,
+            required: None,
+            description: None,
+            items: None,
+            r#enum: None,
+            format: None,
+            minimum: None,
+            maximum: None,
+            nullable: None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -137,6 +178,10 @@ pub struct GenerateContentRequest {
     pub generation_config: GenerationConfig,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tools: Option<Vec<Tool>>,
+    // 綁定一個已建立的 `CachedContent` (格式是 "cachedContents/xxx")。
+    // 有帶這個欄位的話，`contents` 只需要放這一輪新增的內容，快取的部分不用重送。
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cached_content: Option<String>,
 }
 
 // --- Response ---
@@ -156,7 +201,7 @@ pub struct Candidate {
     pub finish_reason: Option<String>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct UsageMetadata {
     pub prompt_token_count: u32,
@@ -166,6 +211,15 @@ pub struct UsageMetadata {
     pub thinking_token_count: Option<u32>, 
 }
 
+// 型別化的 streaming 事件，給 `stream_generate_content_typed` 用。把「思考過程」
+// 和最終答案分開標示，呼叫端不用自己去看 part.thought 那個欄位。
+#[derive(Debug, Clone)]
+pub enum ContentEvent {
+    Text { content: String, thought: bool },
+    FunctionCall(FunctionCall),
+    Usage(UsageMetadata),
+}
+
 // --- Context Caching ---
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
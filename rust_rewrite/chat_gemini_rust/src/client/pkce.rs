@@ -0,0 +1,188 @@
+use anyhow::{Context, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use colored::Colorize;
+use rand::Rng;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const AUTH_ENDPOINT: &str = "https://accounts.google.com/o/oauth2/v2/auth";
+const TOKEN_ENDPOINT: &str = "https://oauth2.googleapis.com/token";
+
+// 包住 refresh/access token 的字串：Debug 印出來一律是遮罩過的內容，避免不小心被
+// log 出去或印在錯誤訊息裡洩漏出去。
+#[derive(Clone)]
+pub struct SecretString(String);
+
+impl SecretString {
+    pub fn new(value: String) -> Self {
+        Self(value)
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SecretString(***)")
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: u64,
+    #[serde(default)]
+    refresh_token: Option<String>,
+}
+
+pub struct PkceTokens {
+    pub access_token: SecretString,
+    pub refresh_token: SecretString,
+    pub expires_at: u64,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
+
+// PKCE 規範要求 code_verifier 是 43~128 個字元的 unreserved characters；
+// 這裡取 64 個隨機 bytes 做 base64url (無 padding) 編碼，長度落在範圍內。
+fn random_code_verifier() -> String {
+    let mut bytes = [0u8; 64];
+    rand::thread_rng().fill(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+fn code_challenge_s256(verifier: &str) -> String {
+    let digest = Sha256::digest(verifier.as_bytes());
+    URL_SAFE_NO_PAD.encode(digest)
+}
+
+// 沒有額外引入 url-encoding 套件，手動處理 query string 裡會出現的幾個保留字元就夠用。
+fn url_encode(raw: &str) -> String {
+    let mut out = String::with_capacity(raw.len());
+    for byte in raw.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                out.push(byte as char);
+            }
+            _ => out.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    out
+}
+
+// 在本機挑一個隨機埠起一個暫時的 HTTP listener，接 Google 導回的 `?code=...`，
+// 收到第一個請求就回應一個簡單的提示頁面並關閉（loopback/installed-app flow）。
+fn capture_redirect_code(listener: &TcpListener) -> Result<String> {
+    let (mut stream, _) = listener.accept().context("等待 OAuth 回呼連線失敗")?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    // 請求行形如 "GET /?code=XXXX&scope=... HTTP/1.1"
+    let path = request_line
+        .split_whitespace()
+        .nth(1)
+        .ok_or_else(|| anyhow::anyhow!("無法解析 OAuth 回呼請求"))?;
+    let query = path.splitn(2, '?').nth(1).unwrap_or("");
+    let code = query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("code="))
+        .ok_or_else(|| anyhow::anyhow!("回呼網址缺少 code 參數"))?
+        .to_string();
+
+    let body = "<html><body>登入完成，請回到終端機繼續。</body></html>";
+    let response = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\n\r\n{}",
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes())?;
+
+    Ok(code)
+}
+
+/// 跑一次完整的 PKCE loopback 互動登入：印出同意頁網址讓使用者在瀏覽器開啟，
+/// 在本機監聽回呼拿到 `code`，再拿 `code_verifier` 去換 access/refresh token。
+pub async fn interactive_login(client_id: &str, scopes: &[&str]) -> Result<PkceTokens> {
+    let listener = TcpListener::bind("127.0.0.1:0").context("無法啟動本機回呼用的監聽埠")?;
+    let port = listener.local_addr()?.port();
+    let redirect_uri = format!("http://127.0.0.1:{}", port);
+
+    let verifier = random_code_verifier();
+    let challenge = code_challenge_s256(&verifier);
+    let scope = scopes.join(" ");
+
+    let auth_url = format!(
+        "{}?client_id={}&redirect_uri={}&response_type=code&scope={}&code_challenge={}&code_challenge_method=S256&access_type=offline&prompt=consent",
+        AUTH_ENDPOINT,
+        url_encode(client_id),
+        url_encode(&redirect_uri),
+        url_encode(&scope),
+        challenge,
+    );
+
+    println!("{}", "請在瀏覽器開啟以下網址完成登入：".blue());
+    println!("{}", auth_url);
+
+    let code = tokio::task::spawn_blocking(move || capture_redirect_code(&listener)).await??;
+
+    let http = reqwest::Client::new();
+    let res = http
+        .post(TOKEN_ENDPOINT)
+        .form(&[
+            ("client_id", client_id),
+            ("code", &code),
+            ("code_verifier", &verifier),
+            ("grant_type", "authorization_code"),
+            ("redirect_uri", &redirect_uri),
+        ])
+        .send()
+        .await?
+        .error_for_status()
+        .context("交換 OAuth token 失敗")?;
+
+    let token: TokenResponse = res.json().await?;
+    let refresh_token = token.refresh_token.ok_or_else(|| {
+        anyhow::anyhow!("Google 沒有回傳 refresh_token（請確認這是第一次授權，且已帶 access_type=offline）")
+    })?;
+    let expires_at = now_secs() + token.expires_in;
+
+    println!("{}", "登入成功，已取得 refresh token。".green());
+
+    Ok(PkceTokens {
+        access_token: SecretString::new(token.access_token),
+        refresh_token: SecretString::new(refresh_token),
+        expires_at,
+    })
+}
+
+/// 用已經持久化的 refresh token 換一組新的 access token。
+pub async fn refresh_access_token(
+    client_id: &str,
+    refresh_token: &SecretString,
+) -> Result<(SecretString, u64)> {
+    let http = reqwest::Client::new();
+    let res = http
+        .post(TOKEN_ENDPOINT)
+        .form(&[
+            ("client_id", client_id),
+            ("refresh_token", refresh_token.expose()),
+            ("grant_type", "refresh_token"),
+        ])
+        .send()
+        .await?
+        .error_for_status()
+        .context("刷新 OAuth token 失敗")?;
+
+    let token: TokenResponse = res.json().await?;
+    let expires_at = now_secs() + token.expires_in;
+    Ok((SecretString::new(token.access_token), expires_at))
+}
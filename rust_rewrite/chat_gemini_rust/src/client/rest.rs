@@ -7,40 +7,146 @@ use std::pin::Pin;
 use futures_util::Stream;
 use bytes::Bytes;
 use std::sync::Arc;
+use anyhow::Context as _;
 
+// 後端選擇：公開的 Generative Language API，或企業用的 Vertex AI。
+// Vertex 走 OAuth/ADC，不使用 API Key，且 URL 結構掛在 GCP 專案/地區之下。
+#[derive(Debug, Clone)]
+pub enum Backend {
+    GenerativeLanguage,
+    Vertex { project_id: String, location: String },
+}
+
+// 指數退避 + full jitter (參考 AWS 架構部落格那篇經典文章) 的重試參數，
+// 429/5xx 或連線層級的錯誤都會重試，其餘 4xx 一律視為不可重試的使用者錯誤。
+#[derive(Debug, Clone)]
+pub struct RetryConfig {
+    pub max_attempts: u32,
+    pub base_ms: u64,
+    pub cap_ms: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self { max_attempts: 4, base_ms: 500, cap_ms: 20_000 }
+    }
+}
+
+// `stream_generate_content` 斷線重連用的狀態：重連時帶著 client 本身的 clone
+// (reqwest::Client/Arc<GoogleAuth> 都很便宜) 跟原始 request，從頭重新打一次
+// streamGenerateContent，把新的 byte stream 接上去繼續餵給呼叫端。
+struct StreamReconnectState {
+    client: GeminiClient,
+    url: String,
+    request: GenerateContentRequest,
+    inner: Pin<Box<dyn Stream<Item = reqwest::Result<Bytes>> + Send>>,
+    attempt: u32,
+}
+
+// streamGenerateContent 解析不出 GenerateContentResponse 時，Gemini 有時候吐的是
+// 這種錯誤信封 (safety block、超過 quota...) 而不是合法的回應 JSON。
+#[derive(Debug, serde::Deserialize)]
+struct ApiErrorEnvelope {
+    error: ApiErrorDetail,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct ApiErrorDetail {
+    code: i32,
+    message: String,
+    #[serde(default)]
+    status: String,
+}
+
+#[derive(Clone)]
 pub struct GeminiClient {
-    pub client: Client, 
+    pub client: Client,
     pub auth: Arc<GoogleAuth>,
-    pub base_url: String, 
+    pub base_url: String,
+    pub backend: Backend,
+    pub retry: RetryConfig,
 }
 
 impl GeminiClient {
-    pub async fn new(auth: Arc<GoogleAuth>) -> Self {
+    pub async fn new(auth: Arc<GoogleAuth>) -> anyhow::Result<Self> {
+        Self::new_with_backend(auth, Backend::GenerativeLanguage).await
+    }
+
+    pub async fn new_vertex(auth: Arc<GoogleAuth>, project_id: String, location: String) -> anyhow::Result<Self> {
+        Self::new_with_backend(auth, Backend::Vertex { project_id, location }).await
+    }
+
+    async fn new_with_backend(auth: Arc<GoogleAuth>, backend: Backend) -> anyhow::Result<Self> {
         let mut headers = HeaderMap::new();
         headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
-        
-        if let Some(key) = auth.get_api_key() {
-             let mut api_key_value = HeaderValue::from_str(key).unwrap_or(HeaderValue::from_static(""));
-             api_key_value.set_sensitive(true);
-             headers.insert("x-goog-api-key", api_key_value);
+
+        // Vertex 一律使用 OAuth bearer token，不附加 API Key header；但 `GoogleAuth`
+        // 一旦設了 `gemini_api_key` 就一律優先解析成 `AuthMethod::ApiKey`，那樣 Vertex
+        // 請求會完全沒有 Authorization header (API Key 不會附加，OAuth token 也拿不到)。
+        // 在這裡就先擋下來，而不是讓請求默默地帶著空 auth 送出去。
+        if matches!(backend, Backend::Vertex { .. }) && auth.get_api_key().is_some() {
+            anyhow::bail!(
+                "Vertex AI 後端需要 OAuth/ADC 憑證，不能用 API Key 認證；\
+                 請改用 Application Default Credentials 或 OAuth 登入 (不要設定 GEMINI_API_KEY)。"
+            );
+        }
+
+        if matches!(backend, Backend::GenerativeLanguage) {
+            if let Some(key) = auth.get_api_key() {
+                 let mut api_key_value = HeaderValue::from_str(key).unwrap_or(HeaderValue::from_static(""));
+                 api_key_value.set_sensitive(true);
+                 headers.insert("x-goog-api-key", api_key_value);
+            }
         }
 
         let client = Client::builder()
             .default_headers(headers)
             .build()
-            .expect("建構 HTTP 客戶端失敗");
+            .context("建構 HTTP 客戶端失敗")?;
 
-        Self {
+        Ok(Self {
             client,
             auth,
             base_url: "https://generativelanguage.googleapis.com/v1beta".to_string(),
-        }
+            backend,
+            retry: RetryConfig::default(),
+        })
     }
-    
+
+    pub fn with_retry_config(mut self, retry: RetryConfig) -> Self {
+        self.retry = retry;
+        self
+    }
+
     pub fn api_key(&self) -> &str {
         self.auth.get_api_key().unwrap_or("")
     }
 
+    // Vertex 上非模型端點 (cachedContents, files...) 所在的前綴。
+    fn vertex_base(project_id: &str, location: &str) -> String {
+        format!("https://{location}-aiplatform.googleapis.com/v1/projects/{project_id}/locations/{location}")
+    }
+
+    pub fn resource_base(&self) -> String {
+        match &self.backend {
+            Backend::GenerativeLanguage => self.base_url.clone(),
+            Backend::Vertex { project_id, location } => Self::vertex_base(project_id, location),
+        }
+    }
+
+    // 組出呼叫模型方法 (embedContent/predict/generateContent) 的完整 URL，
+    // 依目前後端對應到 Vertex 的 publisher-model 路徑。streaming 時 generateContent
+    // 會換成 streamGenerateContent，兩個後端都是如此。
+    pub fn model_endpoint(&self, model: &str, method: &str, streaming: bool) -> String {
+        let method = if streaming && method == "generateContent" { "streamGenerateContent" } else { method };
+        match &self.backend {
+            Backend::GenerativeLanguage => format!("{}/models/{}:{}", self.base_url, model, method),
+            Backend::Vertex { project_id, location } => {
+                format!("{}/publishers/google/models/{}:{}", Self::vertex_base(project_id, location), model, method)
+            }
+        }
+    }
+
     async fn prepare_request(&self, request_builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
         if let Ok(Some(token)) = self.auth.get_token().await {
             request_builder.header(AUTHORIZATION, format!("Bearer {}", token))
@@ -67,17 +173,199 @@ impl GeminiClient {
         model: &str,
         request: &GenerateContentRequest,
     ) -> Result<Pin<Box<dyn Stream<Item = Result<Bytes, Error>> + Send>>, Error> {
-        let url = format!("{}/models/{}:streamGenerateContent?alt=sse", self.base_url, model);
+        let url = format!("{}?alt=sse", self.model_endpoint(model, "generateContent", true));
+
+        let req = self.client.post(&url).json(request);
+        let req = self.prepare_request(req).await;
+        let res = self.send_with_retry(req).await?;
+
+        // SSE 串流一旦開始收資料，連線中途斷掉不會走 `send_with_retry` 那段重試；
+        // Gemini API 沒有可續傳的 streaming 協定，唯一能做的是把整個
+        // streamGenerateContent 請求從頭重送一次，呼叫端收到的還是同一條 byte stream，
+        // 不需要自己處理重連。
+        let state = StreamReconnectState {
+            client: self.clone(),
+            url,
+            request: request.clone(),
+            inner: Box::pin(res.bytes_stream()),
+            attempt: 0,
+        };
 
-        let mut req = self.client.post(&url).json(request);
-        req = self.prepare_request(req).await;
+        let stream = futures_util::stream::unfold(state, |mut state| async move {
+            loop {
+                match state.inner.next().await {
+                    None => return None,
+                    Some(Ok(bytes)) => return Some((Ok(bytes), state)),
+                    Some(Err(e)) => {
+                        let retryable = e.is_connect() || e.is_timeout() || e.is_body() || e.is_request();
+                        if !retryable || state.attempt + 1 >= state.client.retry.max_attempts {
+                            return Some((Err(e), state));
+                        }
+                        state.attempt += 1;
+                        let delay = Self::backoff_delay(&state.client.retry, state.attempt);
+                        tokio::time::sleep(delay).await;
 
-        let res = req.send().await?;
-        let res = res.error_for_status()?;
-        
-        Ok(Box::pin(res.bytes_stream()))
+                        let req = state.client.client.post(&state.url).json(&state.request);
+                        let req = state.client.prepare_request(req).await;
+                        match state.client.send_with_retry(req).await {
+                            Ok(res) => {
+                                state.inner = Box::pin(res.bytes_stream());
+                                continue;
+                            }
+                            Err(e) => return Some((Err(e), state)),
+                        }
+                    }
+                }
+            }
+        });
+
+        Ok(Box::pin(stream))
+    }
+
+    // 依 429/5xx 或連線錯誤重試，退避時間用指數退避 + full jitter；
+    // 有 `Retry-After` header 的話優先照它講的等，沒有才用算出來的退避時間。
+    pub(crate) async fn send_with_retry(&self, mut builder: reqwest::RequestBuilder) -> Result<reqwest::Response, Error> {
+        let mut attempt = 0u32;
+        loop {
+            attempt += 1;
+            let retry_builder = builder.try_clone();
+            let result = builder.send().await;
+
+            let retry_after = match &result {
+                Ok(response) if Self::is_retryable_status(response.status()) => {
+                    Some(Self::retry_after_delay(response.headers()))
+                }
+                Err(e) if e.is_connect() || e.is_timeout() => Some(None),
+                _ => None,
+            };
+
+            if let Some(explicit_delay) = retry_after {
+                if attempt < self.retry.max_attempts {
+                    if let Some(next) = retry_builder {
+                        let delay = explicit_delay.unwrap_or_else(|| Self::backoff_delay(&self.retry, attempt));
+                        tokio::time::sleep(delay).await;
+                        builder = next;
+                        continue;
+                    }
+                }
+            }
+
+            return match result {
+                Ok(response) => response.error_for_status(),
+                Err(e) => Err(e),
+            };
+        }
+    }
+
+    fn is_retryable_status(status: reqwest::StatusCode) -> bool {
+        status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+    }
+
+    // `Retry-After` 可以是秒數 (`"120"`) 或 HTTP-date (`"Wed, 21 Oct 2015 07:28:00 GMT"`)，
+    // 兩種格式 RFC 7231 都允許，伺服器實際回哪一種都要能處理。
+    fn retry_after_delay(headers: &reqwest::header::HeaderMap) -> Option<std::time::Duration> {
+        let value = headers.get(reqwest::header::RETRY_AFTER)?.to_str().ok()?;
+
+        if let Ok(secs) = value.trim().parse::<u64>() {
+            return Some(std::time::Duration::from_secs(secs));
+        }
+
+        let target = chrono::DateTime::parse_from_rfc2822(value.trim()).ok()?;
+        let remaining = target.with_timezone(&chrono::Utc) - chrono::Utc::now();
+        Some(std::time::Duration::from_secs(remaining.num_seconds().max(0) as u64))
+    }
+
+    // Full jitter: 在 [0, min(cap, base * 2^attempt)) 之間均勻取一個延遲時間。
+    fn backoff_delay(retry: &RetryConfig, attempt: u32) -> std::time::Duration {
+        let exp = retry.base_ms.saturating_mul(1u64 << attempt.min(20));
+        let upper = exp.min(retry.cap_ms).max(1);
+        let jittered = rand::random::<u64>() % upper;
+        std::time::Duration::from_millis(jittered)
     }
     
+    /// 跟 [`stream_generate_content`] 一樣打 SSE streaming 端點，但回傳的是已經解析好、
+    /// 逐一拆出來的事件，呼叫端不用自己再處理「一個位元組 chunk 裡可能塞了好幾個
+    /// `data: ...` 事件」或「一行被切在兩個 chunk 中間」這些細節。
+    ///
+    /// 回傳的錯誤用 `anyhow::Error`：串流中途斷線是 [`Error`] (reqwest 的連線錯誤)，
+    /// 但 Gemini 也可能在串流中吐出一個解析不出 [`GenerateContentResponse`] 的錯誤信封
+    /// (safety block、超過 quota...)，那種情況沒有對應的 `reqwest::Error` 可以塞，
+    /// 所以兩種錯誤都統一包成 `anyhow::Error` 往上丟，而不是靜悄悄地把那個事件丟掉。
+    pub async fn stream_generate_content_typed(
+        &self,
+        model: &str,
+        request: &GenerateContentRequest,
+    ) -> Result<Pin<Box<dyn Stream<Item = anyhow::Result<ContentEvent>> + Send>>, Error> {
+        let raw = self.stream_generate_content(model, request).await?;
+        let state = (raw, Vec::<u8>::new(), std::collections::VecDeque::new());
+
+        let decoded = futures_util::stream::unfold(state, |(mut raw, mut buffer, mut pending)| async move {
+            loop {
+                if let Some(event) = pending.pop_front() {
+                    return Some((Ok(event), (raw, buffer, pending)));
+                }
+
+                match raw.next().await {
+                    None => return None,
+                    Some(Err(e)) => return Some((Err(e.into()), (raw, buffer, pending))),
+                    Some(Ok(bytes)) => {
+                        buffer.extend_from_slice(&bytes);
+
+                        // SSE 一行一個事件，但單一個 byte chunk 不保證剛好在行尾切斷，
+                        // 所以沒抓到換行符號前那一段留在 buffer 裡、等下一個 chunk 補完。
+                        while let Some(pos) = buffer.iter().position(|&b| b == b'\n') {
+                            let line = String::from_utf8_lossy(&buffer[..pos]).trim_end_matches('\r').to_string();
+                            buffer.drain(..=pos);
+
+                            if let Some(json_str) = line.strip_prefix("data: ") {
+                                if json_str.trim() == "[DONE]" {
+                                    continue;
+                                }
+                                match serde_json::from_str::<GenerateContentResponse>(json_str) {
+                                    Ok(response) => Self::push_events(response, &mut pending),
+                                    Err(parse_err) => {
+                                        if let Ok(envelope) = serde_json::from_str::<ApiErrorEnvelope>(json_str) {
+                                            let err = anyhow::anyhow!(
+                                                "Gemini API 串流回傳錯誤 {} ({}): {}",
+                                                envelope.error.code, envelope.error.status, envelope.error.message
+                                            );
+                                            return Some((Err(err), (raw, buffer, pending)));
+                                        }
+                                        let err = anyhow::Error::new(parse_err)
+                                            .context(format!("無法解析 SSE 事件: {}", json_str));
+                                        return Some((Err(err), (raw, buffer, pending)));
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        });
+
+        Ok(Box::pin(decoded))
+    }
+
+    fn push_events(response: GenerateContentResponse, pending: &mut std::collections::VecDeque<ContentEvent>) {
+        if let Some(usage) = response.usage_metadata {
+            pending.push_back(ContentEvent::Usage(usage));
+        }
+        if let Some(candidates) = response.candidates {
+            for candidate in candidates {
+                if let Some(content) = candidate.content {
+                    for part in content.parts {
+                        if let Some(text) = part.text {
+                            pending.push_back(ContentEvent::Text { content: text, thought: part.thought.unwrap_or(false) });
+                        }
+                        if let Some(fc) = part.function_call {
+                            pending.push_back(ContentEvent::FunctionCall(fc));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
     pub async fn post(&self, url: &str) -> reqwest::RequestBuilder {
          let mut req = self.client.post(url);
          req = self.prepare_request(req).await;
@@ -89,4 +377,22 @@ impl GeminiClient {
          req = self.prepare_request(req).await;
          req
     }
+
+    pub async fn get(&self, url: &str) -> reqwest::RequestBuilder {
+         let mut req = self.client.get(url);
+         req = self.prepare_request(req).await;
+         req
+    }
+
+    pub async fn patch(&self, url: &str) -> reqwest::RequestBuilder {
+         let mut req = self.client.patch(url);
+         req = self.prepare_request(req).await;
+         req
+    }
+
+    pub async fn delete(&self, url: &str) -> reqwest::RequestBuilder {
+         let mut req = self.client.delete(url);
+         req = self.prepare_request(req).await;
+         req
+    }
 }
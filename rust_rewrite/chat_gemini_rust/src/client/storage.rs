@@ -0,0 +1,139 @@
+use crate::client::auth::GoogleAuth;
+use anyhow::{Result, Context};
+use reqwest::Client;
+use serde::Deserialize;
+use std::sync::Arc;
+use std::time::Duration;
+use chrono::Utc;
+
+// 選擇產出物 (生成的圖片、向量索引) 要存在本地還是 GCS。
+// `ImagenClient::generate_image_to` 和 `SimpleVectorStore::save_to` 都接受同一個目標，
+// 讓使用者可以直接在 config 填一個 `gs://bucket/prefix` 就改存到雲端，
+// 這樣跑在短命容器裡的流程也不會在重啟後把東西全丟掉。
+#[derive(Debug, Clone)]
+pub enum StorageTarget {
+    Local(String),
+    Gcs { bucket: String, prefix: String },
+}
+
+impl StorageTarget {
+    /// 解析 "gs://bucket/prefix" 或一般本地路徑。
+    pub fn parse(dest: &str) -> Self {
+        if let Some(rest) = dest.strip_prefix("gs://") {
+            let mut parts = rest.splitn(2, '/');
+            let bucket = parts.next().unwrap_or_default().to_string();
+            let prefix = parts.next().unwrap_or_default().trim_end_matches('/').to_string();
+            StorageTarget::Gcs { bucket, prefix }
+        } else {
+            StorageTarget::Local(dest.to_string())
+        }
+    }
+
+    fn object_name(&self, filename: &str) -> String {
+        match self {
+            StorageTarget::Local(_) => filename.to_string(),
+            StorageTarget::Gcs { prefix, .. } => {
+                if prefix.is_empty() {
+                    filename.to_string()
+                } else {
+                    format!("{}/{}", prefix, filename)
+                }
+            }
+        }
+    }
+}
+
+/// 一次上傳的結果：本地路徑留空、GCS 則帶回 media link 與（若能簽章）下載用的網址。
+pub struct UploadResult {
+    pub media_link: Option<String>,
+    pub signed_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GcsObject {
+    #[serde(rename = "mediaLink")]
+    media_link: String,
+}
+
+/// 使用 ADC 憑證的簡易 GCS 上傳器，供 `ImagenClient`/`SimpleVectorStore` 共用。
+pub struct GcsUploader {
+    client: Client,
+    auth: Arc<GoogleAuth>,
+}
+
+impl GcsUploader {
+    pub fn new(auth: Arc<GoogleAuth>) -> Self {
+        Self { client: Client::new(), auth }
+    }
+
+    pub async fn upload(&self, target: &StorageTarget, filename: &str, bytes: Vec<u8>, content_type: &str) -> Result<UploadResult> {
+        let StorageTarget::Gcs { bucket, .. } = target else {
+            anyhow::bail!("upload() 只接受 StorageTarget::Gcs");
+        };
+        let object_name = target.object_name(filename);
+
+        let token = self.auth.get_token().await?
+            .context("GCS 上傳需要 OAuth/ADC token，請先設定 Application Default Credentials")?;
+
+        let url = format!(
+            "https://storage.googleapis.com/upload/storage/v1/b/{}/o?uploadType=media&name={}",
+            bucket,
+            urlencoding::encode(&object_name),
+        );
+
+        let res = self.client.post(&url)
+            .bearer_auth(&token)
+            .header("Content-Type", content_type)
+            .body(bytes)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        let obj: GcsObject = res.json().await?;
+        let signed_url = self.signed_download_url(bucket, &object_name, Duration::from_secs(3600)).await.ok();
+
+        Ok(UploadResult { media_link: Some(obj.media_link), signed_url })
+    }
+
+    /// 產生有時效的下載網址。走 IAM Credentials API 的 `signBlob`，
+    /// 這樣就不需要在本機保管服務帳戶的私鑰 (ADC 環境通常也沒有私鑰檔)。
+    pub async fn signed_download_url(&self, bucket: &str, object_name: &str, ttl: Duration) -> Result<String> {
+        let token = self.auth.get_token().await?
+            .context("簽署下載網址需要 OAuth/ADC token")?;
+        let service_account_email = self.auth.service_account_email()
+            .context("簽署下載網址需要知道服務帳戶 email (ADC 未提供)")?;
+
+        let expires_at = Utc::now().timestamp() + ttl.as_secs() as i64;
+        let canonical_resource = format!("/{}/{}", bucket, object_name);
+        let string_to_sign = format!("GET\n\n\n{}\n{}", expires_at, canonical_resource);
+
+        let sign_url = format!(
+            "https://iamcredentials.googleapis.com/v1/projects/-/serviceAccounts/{}:signBlob",
+            service_account_email,
+        );
+
+        #[derive(serde::Serialize)]
+        struct SignBlobRequest { payload: String }
+        #[derive(Deserialize)]
+        struct SignBlobResponse { #[serde(rename = "signedBlob")] signed_blob: String }
+
+        let req = SignBlobRequest { payload: base64::Engine::encode(&base64::engine::general_purpose::STANDARD, string_to_sign.as_bytes()) };
+
+        let res = self.client.post(&sign_url)
+            .bearer_auth(&token)
+            .json(&req)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        let signed: SignBlobResponse = res.json().await?;
+
+        Ok(format!(
+            "https://storage.googleapis.com{}?GoogleAccessId={}&Expires={}&Signature={}",
+            canonical_resource,
+            service_account_email,
+            expires_at,
+            urlencoding::encode(&signed.signed_blob),
+        ))
+    }
+}
@@ -48,11 +48,12 @@ impl<'a> EmbeddingGenerator<'a> {
 
     #[allow(dead_code)]
     pub async fn generate_embedding(&self, text: &str) -> Result<Vec<f32>> {
-        let url = format!("{}/{}:embedContent", self.client.base_url, self.model);
-        
+        let model_id = self.model.trim_start_matches("models/");
+        let url = self.client.model_endpoint(model_id, "embedContent", false);
+
         // Fix: Use client.post which handles auth
         // Previous error: self.client.api_key field access (it's now a method or hidden)
-        
+
         let request = EmbedContentRequest {
             model: self.model.clone(),
             content: ContentPart {
@@ -60,12 +61,10 @@ impl<'a> EmbeddingGenerator<'a> {
             },
         };
 
-        let res: Response = self.client.post(&url).await
-            .json(&request)
-            .send()
-            .await?;
+        let req = self.client.post(&url).await.json(&request);
+        let res: Response = self.client.send_with_retry(req).await?;
 
-        let response: EmbedContentResponse = res.error_for_status()?.json().await?;
+        let response: EmbedContentResponse = res.json().await?;
         Ok(response.embedding.values)
     }
 }
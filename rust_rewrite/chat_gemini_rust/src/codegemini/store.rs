@@ -1,8 +1,15 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs::File;
-use std::io::{BufReader, BufWriter};
-use std::path::Path;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use anyhow::{Result, Context};
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use crate::client::auth::GoogleAuth;
+use crate::client::storage::{StorageTarget, GcsUploader, UploadResult};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct VectorDocument {
@@ -11,21 +18,88 @@ pub struct VectorDocument {
     pub embedding: Vec<f32>,
 }
 
+// 目前的 schema 版本。v1 (沒有這個欄位，serde default 成 0) 只有 embedding，
+// 沒有 BM25 要用的詞頻統計；load() 發現詞頻筆數跟文件數對不上時自動從 content 補算。
+const CURRENT_STORE_VERSION: u32 = 2;
+
+// BM25 標準參數，沿用 Okapi BM25 論文的建議值。
+const BM25_K1: f32 = 1.5;
+const BM25_B: f32 = 0.75;
+
+// Reciprocal Rank Fusion 的平滑常數，沿用原始 RRF 論文建議的 60。
+const RRF_K: f32 = 60.0;
+
+/// 單一文件的 BM25 詞頻統計：詞 -> 出現次數，以及分詞後總長度 (算 avg doc length 要用)。
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+struct LexicalStats {
+    term_freqs: HashMap<String, u32>,
+    doc_len: usize,
+}
+
+fn tokenize(text: &str) -> Vec<String> {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+        .collect()
+}
+
+fn lexical_stats_for(content: &str) -> LexicalStats {
+    let tokens = tokenize(content);
+    let mut term_freqs: HashMap<String, u32> = HashMap::new();
+    for term in &tokens {
+        *term_freqs.entry(term.clone()).or_insert(0) += 1;
+    }
+    LexicalStats { term_freqs, doc_len: tokens.len() }
+}
+
+/// 檢索模式：純語意 (embedding cosine)、純關鍵字 (BM25)，或兩者用 RRF 融合。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchMode {
+    Semantic,
+    Lexical,
+    Hybrid,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct SimpleVectorStore {
     documents: Vec<VectorDocument>,
+    #[serde(default)]
+    lexical: Vec<LexicalStats>,
+    #[serde(default)]
+    version: u32,
 }
 
 impl SimpleVectorStore {
     pub fn new() -> Self {
-        Self { documents: Vec::new() }
+        Self { documents: Vec::new(), lexical: Vec::new(), version: CURRENT_STORE_VERSION }
     }
 
     pub fn add(&mut self, doc: VectorDocument) {
+        self.lexical.push(lexical_stats_for(&doc.content));
         self.documents.push(doc);
+        self.version = CURRENT_STORE_VERSION;
     }
 
     pub fn search(&self, query_vector: &[f32], top_k: usize) -> Vec<(&VectorDocument, f32)> {
+        self.semantic_ranked(query_vector).into_iter().take(top_k).collect()
+    }
+
+    /// 混合檢索：語意排名和 BM25 排名各自算完後用 RRF 融合分數。同義詞查不到的
+    /// 關鍵字、或罕見代號語意抓不準的情況，交給另一邊的排名來補。
+    pub fn search_hybrid(&self, query_vector: &[f32], query_text: &str, top_k: usize, mode: SearchMode) -> Vec<(&VectorDocument, f32)> {
+        match mode {
+            SearchMode::Semantic => self.search(query_vector, top_k),
+            SearchMode::Lexical => self.lexical_ranked(query_text).into_iter().take(top_k).collect(),
+            SearchMode::Hybrid => {
+                let semantic = self.semantic_ranked(query_vector);
+                let lexical = self.lexical_ranked(query_text);
+                rrf_fuse(&semantic, &lexical, top_k)
+            }
+        }
+    }
+
+    fn semantic_ranked(&self, query_vector: &[f32]) -> Vec<(&VectorDocument, f32)> {
         let mut scores: Vec<(&VectorDocument, f32)> = self.documents.iter()
             .map(|doc| {
                 let score = cosine_similarity(&doc.embedding, query_vector);
@@ -35,30 +109,194 @@ impl SimpleVectorStore {
 
         // Sort desc
         scores.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
-        scores.into_iter().take(top_k).collect()
+        scores
     }
-    
+
+    fn lexical_ranked(&self, query: &str) -> Vec<(&VectorDocument, f32)> {
+        self.bm25_scores(query).into_iter().map(|(idx, score)| (&self.documents[idx], score)).collect()
+    }
+
+    fn bm25_scores(&self, query: &str) -> Vec<(usize, f32)> {
+        let query_terms = tokenize(query);
+        if query_terms.is_empty() || self.documents.is_empty() {
+            return Vec::new();
+        }
+
+        let n = self.documents.len() as f32;
+        let avg_doc_len = self.lexical.iter().map(|l| l.doc_len as f32).sum::<f32>() / n;
+
+        let mut doc_freq: HashMap<&str, usize> = HashMap::new();
+        for term in &query_terms {
+            let df = self.lexical.iter().filter(|l| l.term_freqs.contains_key(term)).count();
+            doc_freq.insert(term.as_str(), df);
+        }
+
+        let mut scores: Vec<(usize, f32)> = Vec::new();
+        for (idx, stats) in self.lexical.iter().enumerate() {
+            let mut score = 0.0f32;
+            for term in &query_terms {
+                let df = *doc_freq.get(term.as_str()).unwrap_or(&0);
+                if df == 0 {
+                    continue;
+                }
+                let tf = *stats.term_freqs.get(term).unwrap_or(&0) as f32;
+                if tf == 0.0 {
+                    continue;
+                }
+                let idf = ((n - df as f32 + 0.5) / (df as f32 + 0.5) + 1.0).ln();
+                let denom = tf + BM25_K1 * (1.0 - BM25_B + BM25_B * stats.doc_len as f32 / avg_doc_len);
+                score += idf * (tf * (BM25_K1 + 1.0)) / denom;
+            }
+            if score > 0.0 {
+                scores.push((idx, score));
+            }
+        }
+        scores.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        scores
+    }
+
+    // 舊版 (v1) 存檔沒有 lexical 欄位，load 完發現數量對不上就從 content 重新分詞補算。
+    fn ensure_lexical_index(&mut self) {
+        if self.lexical.len() != self.documents.len() {
+            self.lexical = self.documents.iter().map(|d| lexical_stats_for(&d.content)).collect();
+            self.version = CURRENT_STORE_VERSION;
+        }
+    }
+
     pub fn clear(&mut self) {
         self.documents.clear();
+        self.lexical.clear();
+        self.version = CURRENT_STORE_VERSION;
     }
-    
+
     pub fn count(&self) -> usize {
         self.documents.len()
     }
 
+    // 依副檔名選擇壓縮方式 (.json.zst 優先用 zstd，.json.gz 用 gzip)，
+    // 純 .json 維持原樣以保留回溯相容性。embedding 是稠密的 Vec<f32>，
+    // 文件一多檔案就會很肥，壓縮後體積差很多。
     pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
         let file = File::create(path)?;
         let writer = BufWriter::new(file);
-        serde_json::to_writer(writer, &self)?;
+
+        match Self::compression_of(path) {
+            StoreCompression::Zstd => {
+                let encoder = zstd::Encoder::new(writer, 0)?.auto_finish();
+                serde_json::to_writer(encoder, &self)?;
+            }
+            StoreCompression::Gzip => {
+                let encoder = GzEncoder::new(writer, Compression::default());
+                serde_json::to_writer(encoder, &self)?;
+            }
+            StoreCompression::None => {
+                serde_json::to_writer(writer, &self)?;
+            }
+        }
         Ok(())
     }
 
     pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
         let file = File::open(path)?;
         let reader = BufReader::new(file);
-        let store = serde_json::from_reader(reader).context("Failed to parse vector store JSON")?;
+
+        let mut store: Self = match Self::compression_of(path) {
+            StoreCompression::Zstd => {
+                let decoder = zstd::Decoder::new(reader)?;
+                serde_json::from_reader(decoder).context("Failed to parse vector store JSON (zstd)")?
+            }
+            StoreCompression::Gzip => {
+                let decoder = GzDecoder::new(reader);
+                serde_json::from_reader(decoder).context("Failed to parse vector store JSON (gzip)")?
+            }
+            StoreCompression::None => {
+                serde_json::from_reader(reader).context("Failed to parse vector store JSON")?
+            }
+        };
+        store.ensure_lexical_index();
         Ok(store)
     }
+
+    /// 存到本地目錄或 `StorageTarget::Gcs` 指定的 bucket/prefix。GCS 情況下會先在
+    /// 記憶體中依 `filename` 副檔名完成壓縮，再整包上傳，不落地任何暫存檔案。
+    pub async fn save_to(&self, target: &StorageTarget, filename: &str, auth: Arc<GoogleAuth>) -> Result<Option<UploadResult>> {
+        match target {
+            StorageTarget::Local(dir) => {
+                let path = PathBuf::from(dir).join(filename);
+                self.save(path)?;
+                Ok(None)
+            }
+            StorageTarget::Gcs { .. } => {
+                let bytes = self.serialize_compressed(Path::new(filename))?;
+                let uploader = GcsUploader::new(auth);
+                let uploaded = uploader.upload(target, filename, bytes, "application/json").await?;
+                Ok(Some(uploaded))
+            }
+        }
+    }
+
+    fn serialize_compressed(&self, name_hint: &Path) -> Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        match Self::compression_of(name_hint) {
+            StoreCompression::Zstd => {
+                let mut encoder = zstd::Encoder::new(&mut buf, 0)?;
+                serde_json::to_writer(&mut encoder, &self)?;
+                encoder.finish()?;
+            }
+            StoreCompression::Gzip => {
+                let mut encoder = GzEncoder::new(&mut buf, Compression::default());
+                serde_json::to_writer(&mut encoder, &self)?;
+                encoder.finish()?;
+            }
+            StoreCompression::None => {
+                serde_json::to_writer(&mut buf, &self)?;
+            }
+        }
+        Ok(buf)
+    }
+
+    fn compression_of(path: &Path) -> StoreCompression {
+        let name = path.to_string_lossy();
+        if name.ends_with(".json.zst") {
+            StoreCompression::Zstd
+        } else if name.ends_with(".json.gz") {
+            StoreCompression::Gzip
+        } else {
+            StoreCompression::None
+        }
+    }
+}
+
+enum StoreCompression {
+    None,
+    Zstd,
+    Gzip,
+}
+
+// 兩邊排名各自算出 RRF 分數後加總；同一份文件在兩邊都上榜會拿到兩份分數的疊加。
+fn rrf_fuse<'a>(
+    semantic: &[(&'a VectorDocument, f32)],
+    lexical: &[(&'a VectorDocument, f32)],
+    top_k: usize,
+) -> Vec<(&'a VectorDocument, f32)> {
+    let mut fused: HashMap<*const VectorDocument, (&'a VectorDocument, f32)> = HashMap::new();
+
+    for (rank, (doc, _)) in semantic.iter().enumerate() {
+        let key = *doc as *const VectorDocument;
+        let entry = fused.entry(key).or_insert((doc, 0.0));
+        entry.1 += 1.0 / (RRF_K + rank as f32 + 1.0);
+    }
+    for (rank, (doc, _)) in lexical.iter().enumerate() {
+        let key = *doc as *const VectorDocument;
+        let entry = fused.entry(key).or_insert((doc, 0.0));
+        entry.1 += 1.0 / (RRF_K + rank as f32 + 1.0);
+    }
+
+    let mut results: Vec<(&VectorDocument, f32)> = fused.into_values().collect();
+    results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    results.into_iter().take(top_k).collect()
 }
 
 fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
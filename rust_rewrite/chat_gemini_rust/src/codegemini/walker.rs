@@ -1,12 +1,46 @@
 use ignore::WalkBuilder;
 use std::path::Path;
+use std::fs::File;
+use std::io::Read;
 use anyhow::Result;
 use std::fs;
 
+// 只看前幾 KiB 就能判斷是不是二進位檔，不用把整個大檔讀完再丟掉。
+const SNIFF_SIZE: usize = 8 * 1024;
+// 非文字位元組比例超過這個門檻就視為二進位檔。
+const BINARY_RATIO_THRESHOLD: f64 = 0.3;
+const DEFAULT_MAX_SIZE: u64 = 2 * 1024 * 1024; // 2 MiB
+
+// 編譯產物、鎖檔這類即使是文字也沒有索引價值的副檔名，預設直接跳過。
+const DEFAULT_DENY_EXTENSIONS: &[&str] = &[
+    "lock", "min.js", "map", "png", "jpg", "jpeg", "gif", "bmp", "ico", "webp",
+    "pdf", "zip", "tar", "gz", "7z", "exe", "dll", "so", "dylib", "class", "o", "a",
+];
+
+pub struct WalkOptions {
+    pub max_size: u64,
+    pub allow_extensions: Option<Vec<String>>,
+    pub deny_extensions: Vec<String>,
+}
+
+impl Default for WalkOptions {
+    fn default() -> Self {
+        Self {
+            max_size: DEFAULT_MAX_SIZE,
+            allow_extensions: None,
+            deny_extensions: DEFAULT_DENY_EXTENSIONS.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+}
+
 pub struct FileWalker;
 
 impl FileWalker {
     pub fn walk(path: &Path) -> Result<Vec<(String, String)>> {
+        Self::walk_with_options(path, &WalkOptions::default())
+    }
+
+    pub fn walk_with_options(path: &Path, options: &WalkOptions) -> Result<Vec<(String, String)>> {
         let mut files = Vec::new();
         let walker = WalkBuilder::new(path)
             .hidden(false) // Allow hidden files if gitignore doesn't hide them? No, usually keep defaults.
@@ -18,8 +52,23 @@ impl FileWalker {
                 Ok(entry) => {
                     if entry.file_type().map_or(false, |ft| ft.is_file()) {
                         let path = entry.path();
-                        // Filter binary files naively by extension or mimetype check?
-                        // For MVP, allow common code extensions or try to read as utf8.
+
+                        if !Self::extension_allowed(path, options) {
+                            continue;
+                        }
+
+                        let metadata = match fs::metadata(path) {
+                            Ok(m) => m,
+                            Err(_) => continue,
+                        };
+                        if metadata.len() > options.max_size {
+                            continue;
+                        }
+
+                        if !Self::looks_like_text(path) {
+                            continue;
+                        }
+
                         if let Ok(content) = fs::read_to_string(path) {
                             if !content.trim().is_empty() {
                                 files.push((path.to_string_lossy().to_string(), content));
@@ -32,4 +81,57 @@ impl FileWalker {
         }
         Ok(files)
     }
+
+    fn extension_allowed(path: &Path, options: &WalkOptions) -> bool {
+        let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+        let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("").to_lowercase();
+
+        // `Path::extension()` 只回傳最後一個點之後的那段，像 "min.js" 這種多段副檔名
+        // 永遠比對不到 (foo.min.js 的 extension() 是 "js")，所以含點的 deny 項目改成
+        // 直接比對檔名結尾；單段的維持原本跟 extension() 比對的寫法。
+        let is_denied = options.deny_extensions.iter().any(|d| {
+            let d = d.to_lowercase();
+            if d.contains('.') {
+                file_name.ends_with(&format!(".{d}"))
+            } else {
+                ext == d
+            }
+        });
+        if is_denied {
+            return false;
+        }
+
+        match &options.allow_extensions {
+            Some(allowed) => allowed.iter().any(|a| a.eq_ignore_ascii_case(&ext)),
+            None => true,
+        }
+    }
+
+    // 只偷看檔案開頭一小段：含 NUL 位元組，或非文字位元組比例太高，就當成二進位檔跳過。
+    fn looks_like_text(path: &Path) -> bool {
+        let mut file = match File::open(path) {
+            Ok(f) => f,
+            Err(_) => return false,
+        };
+
+        let mut buf = vec![0u8; SNIFF_SIZE];
+        let n = match file.read(&mut buf) {
+            Ok(n) => n,
+            Err(_) => return false,
+        };
+        if n == 0 {
+            return true; // 空檔案視為文字，交給後面的 `content.trim().is_empty()` 過濾
+        }
+        let sample = &buf[..n];
+
+        if sample.contains(&0) {
+            return false;
+        }
+
+        let non_text = sample.iter()
+            .filter(|&&b| b != b'\n' && b != b'\r' && b != b'\t' && (b < 0x20 || b == 0x7f))
+            .count();
+
+        (non_text as f64 / sample.len() as f64) < BINARY_RATIO_THRESHOLD
+    }
 }
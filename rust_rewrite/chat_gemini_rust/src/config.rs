@@ -18,9 +18,63 @@ pub struct Settings {
     pub oauth_client_id: Option<String>,
     pub oauth_client_secret: Option<String>,
     pub oauth_secret_file: Option<String>, // Path to client_secret.json
-    
+
+    // Vertex AI Configuration (選用；有填 project_id 才會切換到 Vertex 後端)
+    pub vertex_project_id: Option<String>,
+    pub vertex_location: Option<String>,
+
+    // Mastodon 分享 (選用；兩個都填了才能用 /image ... --share-mastodon)
+    pub mastodon_instance_url: Option<String>,
+    pub mastodon_access_token: Option<String>,
+
+    // Agent loop 連續自動呼叫工具的步數上限，避免模型卡在無窮迴圈燒 token。
+    pub max_agent_steps: usize,
+    // 設定了就把 agent loop 每一步事件 (使用者輸入、工具呼叫、模型回覆) 寫成 JSONL。
+    pub log_file: Option<String>,
+
+    // HTTP 請求的重試參數 (429/5xx 或連線錯誤時指數退避 + full jitter)。
+    pub retry_max_attempts: u32,
+    pub retry_base_ms: u64,
+    pub retry_cap_ms: u64,
+
+    // 產出物 (生成圖片、向量索引) 的存放位置；留白就存在本地，填 `gs://bucket/prefix`
+    // 就改用 `StorageTarget::Gcs` 上傳到雲端。
+    pub storage_target: Option<String>,
+
+    // 填了就優先嘗試 PKCE loopback 互動登入 (免 client_secret)，細節見 `GoogleAuth::login_pkce`。
+    pub oauth_pkce_client_id: Option<String>,
+
     #[serde(default)]
     pub mcp: McpConfig,
+
+    #[serde(default)]
+    pub tool_policy: ToolPolicyConfig,
+}
+
+// 工具執行的核准政策：哪些工具已預先授權可以直接跑，或乾脆對所有工具都要求確認。
+#[derive(Debug, Deserialize, Clone)]
+pub struct ToolPolicyConfig {
+    #[serde(default)]
+    pub auto_approve: Vec<String>,
+    #[serde(default)]
+    pub confirm_all: bool,
+    // 同時間最多平行執行幾個 MCP 工具呼叫（依 server 分組，同一個 server 仍是依序呼叫）。
+    #[serde(default = "default_max_parallel_tools")]
+    pub max_parallel_tools: usize,
+}
+
+fn default_max_parallel_tools() -> usize {
+    4
+}
+
+impl Default for ToolPolicyConfig {
+    fn default() -> Self {
+        Self {
+            auto_approve: Vec::new(),
+            confirm_all: false,
+            max_parallel_tools: default_max_parallel_tools(),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Clone, Default)]
@@ -47,7 +101,18 @@ impl Settings {
             .set_default("oauth_client_id", None::<String>)?
             .set_default("oauth_client_secret", None::<String>)?
             .set_default("oauth_secret_file", None::<String>)?
-            
+            .set_default("vertex_project_id", None::<String>)?
+            .set_default("vertex_location", "us-central1")?
+            .set_default("mastodon_instance_url", None::<String>)?
+            .set_default("mastodon_access_token", None::<String>)?
+            .set_default("max_agent_steps", 8)?
+            .set_default("log_file", None::<String>)?
+            .set_default("retry_max_attempts", 4)?
+            .set_default("retry_base_ms", 500)?
+            .set_default("retry_cap_ms", 20_000)?
+            .set_default("storage_target", None::<String>)?
+            .set_default("oauth_pkce_client_id", None::<String>)?
+
             .add_source(File::with_name("config").required(false))
             .add_source(Environment::with_prefix("GEMINI").separator("_"));
 
@@ -2,6 +2,7 @@ use std::io::{self, Write};
 use std::fs;
 use colored::Colorize;
 use futures_util::StreamExt;
+use serde_json::Value;
 use base64::prelude::*;
 use base64::Engine;
 use mime_guess::from_path;
@@ -20,6 +21,7 @@ mod mcp;
 use config::Settings;
 use client::rest::GeminiClient;
 use client::auth::GoogleAuth;
+use client::storage::StorageTarget;
 use client::models::{GenerateContentRequest, Content, Part, GenerationConfig, ThinkingConfig, CachedContent, FunctionCall, FunctionResponse};
 use client::cache::CacheManager;
 use client::files::FileManager;
@@ -28,6 +30,8 @@ use chat::session::ChatSession;
 use chat::input_parser::parse_input;
 use chat::pricing::PricingCalculator;
 use chat::commands::{handle_command, AppState};
+use chat::approval::ApprovalGate;
+use chat::transcript::{self, TranscriptLogger};
 use codegemini::store::SimpleVectorStore;
 use codegemini::embeddings::EmbeddingGenerator;
 use ui::prompt::Repl;
@@ -75,8 +79,41 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     };
 
     // 2. 初始化核心組件
-    let client = GeminiClient::new(auth).await; 
-    let mut session = ChatSession::new();
+    let client = match &settings.vertex_project_id {
+        Some(project_id) if !project_id.is_empty() => {
+            let location = settings.vertex_location.clone().unwrap_or_else(|| "us-central1".to_string());
+            println!("{}", format!("已啟用 Vertex AI 後端 (project={}, location={})", project_id, location).cyan());
+            GeminiClient::new_vertex(auth, project_id.clone(), location).await
+        }
+        _ => GeminiClient::new(auth).await,
+    };
+    let client = match client {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("{} {}", "初始化 GeminiClient 失敗:".red(), e);
+            std::process::exit(1);
+        }
+    }.with_retry_config(client::rest::RetryConfig {
+        max_attempts: settings.retry_max_attempts,
+        base_ms: settings.retry_base_ms,
+        cap_ms: settings.retry_cap_ms,
+    });
+    // `--resume <name>` 啟動旗標：直接載入指定的已儲存 session，而不是從空白開始。
+    let cli_args: Vec<String> = std::env::args().collect();
+    let resume_name = cli_args.iter().position(|a| a == "--resume").and_then(|i| cli_args.get(i + 1)).cloned();
+    let mut session = match resume_name {
+        Some(name) => match ChatSession::load(&name) {
+            Ok(loaded) => {
+                println!("{} 已還原 session '{}' ({} 則訊息)", "✓".green(), name, loaded.history.len());
+                loaded
+            }
+            Err(e) => {
+                eprintln!("無法還原 session '{}': {}，改用新的 session", name, e);
+                ChatSession::new()
+            }
+        },
+        None => ChatSession::new(),
+    };
     let mut repl = Repl::new();
     let skin = create_skin(); 
     let pricing = PricingCalculator::new(32.5);
@@ -93,7 +130,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // 初始化 Context Caching
     let cache_manager = CacheManager::new(&client);
-    let mut active_cache_name: Option<String> = None;
+    let mut active_cache_name: Option<String> = session.active_cache_name.clone();
 
     // 初始化 File Manager & Imagen Client
     let file_manager = FileManager::new(&client);
@@ -101,6 +138,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // 初始化 MCP Client Store
     let mut mcp_clients: HashMap<String, McpClient> = HashMap::new();
+    let mut approval_gate = ApprovalGate::new();
+    let transcript = TranscriptLogger::new(settings.log_file.clone());
+
+    // 設定了 `storage_target` 就把生成圖片/向量索引改存到 `gs://bucket/prefix`。
+    let storage_target = settings.storage_target.as_ref().map(|s| StorageTarget::parse(s));
+    let storage_auth = client.auth.clone();
 
     println!("{}", t!("welcome").purple().bold());
     println!("{}", t!("model_current", model = settings.model_name).cyan());
@@ -123,6 +166,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         embedding_generator: &embedding_generator,
                         mcp_clients: &mut mcp_clients,
                         imagen_client: &imagen_client,
+                        cache_manager: &cache_manager,
+                        active_cache_name: &mut active_cache_name,
+                        storage_target: storage_target.as_ref(),
+                        auth: storage_auth.clone(),
                     };
                     
                     if !handle_command(line, &mut state).await {
@@ -193,7 +240,19 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     if size > MAX_INLINE_SIZE {
                          println!("{}", t!("file_uploading", path = path.display()).blue());
                          match file_manager.upload(path, &mime_str).await {
-                            Ok(file_data) => {
+                            Ok(mut file_data) => {
+                                // 影片/音訊上傳完通常還在 PROCESSING，要等轉成 ACTIVE
+                                // 才能在 generateContent 裡被 file_uri 引用。
+                                if file_data.state == "PROCESSING" {
+                                    println!("{}", t!("file_processing", name = file_data.name).blue());
+                                    file_data = match file_manager.wait_until_active(&file_data.name).await {
+                                        Ok(active) => active,
+                                        Err(e) => {
+                                            eprintln!("{} {}", "✗".red(), t!("file_upload_failed", error = e));
+                                            continue;
+                                        }
+                                    };
+                                }
                                 println!("{} {}", "✓".green(), t!("file_uploaded", uri = file_data.uri));
                                 content_parts.push(Part::file_data(mime_str.clone(), file_data.uri));
                             },
@@ -229,8 +288,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 }
 
                 session.add_user_message(&parsed.text);
+                transcript.log(&transcript::event("user_message", &parsed.text));
 
                 // --- Agent Loop ---
+                let mut agent_step = 0usize;
                 loop {
                     let mut gemini_tools = Vec::new();
                     for (name, client) in mcp_clients.iter_mut() {
@@ -240,14 +301,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     }
                     let tools_option = if gemini_tools.is_empty() { None } else { Some(gemini_tools) };
 
-                    let (request_contents, request_model) = if let Some(ref cache_name) = active_cache_name {
-                        (vec![Content {
+                    let request_contents = if active_cache_name.is_some() {
+                        vec![Content {
                             role: "user".to_string(),
-                            parts: content_parts.clone(), 
-                        }], cache_name.clone())
+                            parts: content_parts.clone(),
+                        }]
                     } else {
-                        let mut full_contents = session.to_gemini_history();
-                        (full_contents, settings.model_name.clone())
+                        session.to_gemini_history()
                     };
                     
                     let thinking_config = if let Some(budget) = parsed.thinking_budget {
@@ -273,6 +333,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                             thinking_config,
                         },
                         tools: tools_option,
+                        cached_content: active_cache_name.clone(),
                     };
 
                     print!("{}", t!("input_prompt"));
@@ -282,38 +343,20 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     let mut function_calls: Vec<FunctionCall> = Vec::new();
                     let mut usage_meta = None;
 
-                    match client.stream_generate_content(&request_model, &request).await {
+                    match client.stream_generate_content_typed(&settings.model_name, &request).await {
                         Ok(mut stream) => {
-                            while let Some(chunk_result) = stream.next().await {
-                                match chunk_result {
-                                    Ok(bytes) => {
-                                        let s = String::from_utf8_lossy(&bytes);
-                                        for line in s.lines() {
-                                            if line.starts_with("data: ") {
-                                                let json_str = &line[6..];
-                                                if json_str.trim() == "[DONE]" { continue; }
-                                                
-                                                if let Ok(response) = serde_json::from_str::<client::models::GenerateContentResponse>(json_str) {
-                                                    if let Some(meta) = response.usage_metadata { usage_meta = Some(meta); }
-                                                    if let Some(candidates) = response.candidates {
-                                                        for candidate in candidates {
-                                                            if let Some(content) = candidate.content {
-                                                                for part in content.parts {
-                                                                    if let Some(text) = part.text {
-                                                                        print!("{}", text);
-                                                                        io::stdout().flush()?;
-                                                                        full_response_text.push_str(&text);
-                                                                    }
-                                                                    if let Some(fc) = part.function_call {
-                                                                        function_calls.push(fc);
-                                                                    }
-                                                                }
-                                                            }
-                                                        }
-                                                    }
-                                                }
-                                            }
-                                        }
+                            while let Some(event_result) = stream.next().await {
+                                match event_result {
+                                    Ok(client::models::ContentEvent::Text { content, thought: _ }) => {
+                                        print!("{}", content);
+                                        io::stdout().flush()?;
+                                        full_response_text.push_str(&content);
+                                    }
+                                    Ok(client::models::ContentEvent::FunctionCall(fc)) => {
+                                        function_calls.push(fc);
+                                    }
+                                    Ok(client::models::ContentEvent::Usage(meta)) => {
+                                        usage_meta = Some(meta);
                                     }
                                     Err(e) => eprintln!("\nStream Error: {}", e),
                                 }
@@ -321,7 +364,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         }
                         Err(e) => {
                             eprintln!("\nAPI Error: {}", e);
-                            break; 
+                            break;
                         }
                     }
                     println!();
@@ -334,68 +377,196 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         for fc in &function_calls {
                             parts.push(Part {
                                 text: None, inline_data: None, file_data: None, function_response: None,
-                                function_call: Some(fc.clone()),
+                                function_call: Some(fc.clone()), thought: None,
                             });
                         }
                         session.add_message("model", parts);
 
-                        let mut responses = Vec::new();
-                        for call in function_calls {
-                            println!("{}", format!("🛠 Executing Tool: {}", call.name).yellow());
-                            
+                        // 先留一份 name+args，tool_round 事件要把每通呼叫實際收到的參數寫進 transcript，
+                        // 但下面馬上就要把 function_calls 消耗掉 (into_iter)，所以提前複製。
+                        let call_log: Vec<(String, Value)> = function_calls.iter()
+                            .map(|c| (c.name.clone(), c.args.clone()))
+                            .collect();
+
+                        // 第一輪：依序做核准判斷 (可能需要跟使用者互動，不能平行)。
+                        // 核准過的呼叫記下原本的順序 index，第二輪才依 server 分組平行執行。
+                        let mut tool_results: Vec<Option<FunctionResponse>> = (0..function_calls.len()).map(|_| None).collect();
+                        let mut pending: HashMap<String, Vec<(usize, String, Value)>> = HashMap::new();
+
+                        for (idx, call) in function_calls.into_iter().enumerate() {
                             let parts: Vec<&str> = call.name.splitn(2, "__").collect();
-                            if parts.len() == 2 {
-                                let server_name = parts[0];
-                                let tool_name = parts[1];
-                                
-                                if let Some(mcp_client) = mcp_clients.get_mut(server_name) {
-                                    match mcp_client.call_tool(tool_name, call.args.clone()) {
+                            if parts.len() != 2 {
+                                tool_results[idx] = Some(FunctionResponse {
+                                    name: call.name.clone(),
+                                    response: serde_json::json!({ "error": "Invalid tool name format" }),
+                                });
+                                continue;
+                            }
+                            let server_name = parts[0].to_string();
+                            let tool_name = parts[1].to_string();
+
+                            if !approval_gate.check(&call.name, &tool_name, &call.args, &settings) {
+                                println!("{}", format!("✗ 已拒絕執行: {}", call.name).red());
+                                tool_results[idx] = Some(FunctionResponse {
+                                    name: call.name.clone(),
+                                    response: serde_json::json!({ "error": "rejected by user" }),
+                                });
+                                continue;
+                            }
+
+                            if !mcp_clients.contains_key(&server_name) {
+                                tool_results[idx] = Some(FunctionResponse {
+                                    name: call.name.clone(),
+                                    response: serde_json::json!({ "error": "MCP Server not found" }),
+                                });
+                                continue;
+                            }
+
+                            pending.entry(server_name).or_default().push((idx, tool_name, call.args.clone()));
+                        }
+
+                        // 第二輪：把會用到的 McpClient 暫時搬出 HashMap，丟進 blocking task 平行跑。
+                        // 同一個 server 底下是同一個 child process 用 stdio 溝通，呼叫仍然照順序跑；
+                        // 不同 server 之間才是真正平行，平行度上限由 tool_policy.max_parallel_tools 控制。
+                        let max_parallel = settings.tool_policy.max_parallel_tools.max(1);
+                        let server_names: Vec<String> = pending.keys().cloned().collect();
+                        let mut workers = Vec::new();
+                        for server_name in server_names {
+                            let jobs = pending.remove(&server_name).unwrap_or_default();
+                            if let Some(mcp_client) = mcp_clients.remove(&server_name) {
+                                workers.push((server_name, mcp_client, jobs));
+                            }
+                        }
+
+                        let tasks = workers.into_iter().map(|(server_name, mut mcp_client, jobs)| {
+                            // worker 如果整個 panic 掉 (spawn_blocking 回傳 JoinError)，連帶被搬進去的
+                            // `mcp_client`、`jobs` 都會跟著那條執行緒的 stack 一起消失，事後沒辦法再
+                            // 從 `JoinError` 裡撈回來。所以先在 spawn 之前把這批呼叫的 idx/name 另外
+                            // 存一份，worker 失敗時才能補發錯誤 `FunctionResponse`，而不是讓
+                            // `tool_results` 裡悄悄留著 `None`。
+                            let job_labels: Vec<(usize, String)> = jobs.iter()
+                                .map(|(idx, tool_name, _)| (*idx, format!("{}__{}", server_name, tool_name)))
+                                .collect();
+                            let server_name_for_err = server_name.clone();
+
+                            let handle = tokio::task::spawn_blocking(move || {
+                                let mut results = Vec::new();
+                                for (idx, tool_name, args) in jobs {
+                                    println!("{}", format!("🛠 Executing Tool: {}__{}", server_name, tool_name).yellow());
+                                    let response = match mcp_client.call_tool(&tool_name, args) {
                                         Ok(result) => {
                                             println!("  -> Result: {}", result.to_string().chars().take(50).collect::<String>());
-                                            responses.push(FunctionResponse {
-                                                name: call.name.clone(),
+                                            FunctionResponse {
+                                                name: format!("{}__{}", server_name, tool_name),
                                                 response: serde_json::json!({ "result": result }),
-                                            });
-                                        },
+                                            }
+                                        }
                                         Err(e) => {
                                             eprintln!("  -> Error: {}", e);
-                                            responses.push(FunctionResponse {
-                                                name: call.name.clone(),
+                                            FunctionResponse {
+                                                name: format!("{}__{}", server_name, tool_name),
                                                 response: serde_json::json!({ "error": e.to_string() }),
-                                            });
+                                            }
                                         }
+                                    };
+                                    results.push((idx, response));
+                                }
+                                (server_name, mcp_client, results)
+                            });
+
+                            async move { (server_name_for_err, job_labels, handle.await) }
+                        });
+
+                        let outcomes: Vec<_> = futures_util::stream::iter(tasks)
+                            .buffer_unordered(max_parallel)
+                            .collect()
+                            .await;
+
+                        for (server_name, job_labels, outcome) in outcomes {
+                            match outcome {
+                                Ok((server_name, mcp_client, results)) => {
+                                    mcp_clients.insert(server_name, mcp_client);
+                                    for (idx, response) in results {
+                                        tool_results[idx] = Some(response);
+                                    }
+                                }
+                                Err(e) => {
+                                    // `mcp_client` 跟著 panic 掉的執行緒一起沒了，這個 server 這一輪
+                                    // 確實用不了，但至少讓模型看到每一通呼叫都有明確的錯誤回應，
+                                    // 不要無聲無息地把它們從 `tool_results` 裡漏掉。
+                                    eprintln!("  -> Tool worker 崩潰 ({}): {}", server_name, e);
+                                    for (idx, name) in job_labels {
+                                        tool_results[idx] = Some(FunctionResponse {
+                                            name,
+                                            response: serde_json::json!({ "error": format!("tool worker crashed: {}", e) }),
+                                        });
                                     }
-                                } else {
-                                     responses.push(FunctionResponse {
-                                        name: call.name.clone(),
-                                        response: serde_json::json!({ "error": "MCP Server not found" }),
-                                    });
                                 }
-                            } else {
-                                responses.push(FunctionResponse {
-                                    name: call.name.clone(),
-                                    response: serde_json::json!({ "error": "Invalid tool name format" }),
-                                });
                             }
                         }
 
+                        let responses: Vec<FunctionResponse> = tool_results.into_iter().flatten().collect();
+
+                        // 每通呼叫的 name/args 跟對應的 result-or-error 配對記錄下來，
+                        // 這兩個 vec 順序一致 (都是照原始 idx 排的)，才 zip 得起來。
+                        let call_records: Vec<Value> = call_log.iter().zip(responses.iter())
+                            .map(|((name, args), fr)| serde_json::json!({
+                                "name": name,
+                                "args": args,
+                                "response": fr.response,
+                            }))
+                            .collect();
+
                         let mut response_parts = Vec::new();
                         for fr in responses {
                             response_parts.push(Part {
                                 text: None, inline_data: None, file_data: None, function_call: None,
-                                function_response: Some(fr),
+                                function_response: Some(fr), thought: None,
                             });
                         }
                         session.add_message("function", response_parts);
+                        transcript.log(&transcript::event("tool_round", serde_json::json!({
+                            "step": agent_step + 1,
+                            "calls": call_records,
+                            "usage_metadata": usage_meta,
+                        })));
+
+                        agent_step += 1;
+                        if agent_step >= settings.max_agent_steps {
+                            println!("{}", format!("⚠ 已達到 agent 最大步數上限 ({})，停止自動呼叫工具", settings.max_agent_steps).yellow());
+                            session.add_message("function", vec![Part {
+                                text: None, inline_data: None, file_data: None, function_call: None, thought: None,
+                                function_response: Some(FunctionResponse {
+                                    name: "system".to_string(),
+                                    response: serde_json::json!({
+                                        "error": format!("agent loop stopped: exceeded max_agent_steps ({})", settings.max_agent_steps)
+                                    }),
+                                }),
+                            }]);
+                            transcript.log(&transcript::event("agent_loop_truncated", serde_json::json!({ "max_steps": settings.max_agent_steps })));
+                            break;
+                        }
                         continue;
 
                     } else {
                         if !full_response_text.is_empty() {
                              session.add_model_message(&full_response_text);
+                             transcript.log(&transcript::event("model_message", serde_json::json!({
+                                 "text": full_response_text,
+                                 "usage_metadata": usage_meta,
+                             })));
                         }
 
                         if let Some(meta) = usage_meta {
-                            let (usd, twd) = pricing.calculate(&settings.model_name, meta.prompt_token_count, meta.candidates_token_count);
+                            // Vertex 依地區收費，跟公開 Generative Language API 的費率不同。
+                            let (usd, twd) = match &client.backend {
+                                client::rest::Backend::Vertex { location, .. } => {
+                                    pricing.calculate_vertex(&settings.model_name, location, meta.prompt_token_count, meta.candidates_token_count)
+                                }
+                                client::rest::Backend::GenerativeLanguage => {
+                                    pricing.calculate(&settings.model_name, meta.prompt_token_count, meta.candidates_token_count)
+                                }
+                            };
                             println!("{}", "─".repeat(60).truecolor(181, 101, 216));
                             println!("{}", t!("cost_info", twd = format!("{:.4}", twd), usd = format!("{:.6}", usd), total = meta.total_token_count, input = meta.prompt_token_count, output = meta.candidates_token_count));
                         }
@@ -1,17 +1,27 @@
-use std::process::{Command, Stdio};
+use std::collections::HashMap;
 use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
 use anyhow::{Result, Context};
 use crate::mcp::protocol::*;
 use serde_json::Value;
 
+type PendingMap = Arc<Mutex<HashMap<u64, Sender<JsonRpcResponse>>>>;
+
 pub struct McpClient {
-    child: std::process::Child,
+    child: Child,
+    stdin: ChildStdin,
     next_id: u64,
+    pending: PendingMap,
+    notifications: Mutex<Receiver<JsonRpcNotification>>,
+    _reader: JoinHandle<()>,
 }
 
 impl McpClient {
     pub fn new(command: &str, args: &[&str]) -> Result<Self> {
-        let child = Command::new(command)
+        let mut child = Command::new(command)
             .args(args)
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
@@ -19,11 +29,74 @@ impl McpClient {
             .spawn()
             .context(format!("Failed to spawn MCP server: {}", command))?;
 
-        let mut client = Self { child, next_id: 1 };
+        let stdin = child.stdin.take().context("Failed to get stdin")?;
+        let stdout = child.stdout.take().context("Failed to get stdout")?;
+
+        let pending: PendingMap = Arc::new(Mutex::new(HashMap::new()));
+        let (notif_tx, notif_rx) = mpsc::channel();
+        let reader = Self::spawn_reader(stdout, pending.clone(), notif_tx);
+
+        let mut client = Self {
+            child,
+            stdin,
+            next_id: 1,
+            pending,
+            notifications: Mutex::new(notif_rx),
+            _reader: reader,
+        };
         client.initialize()?;
         Ok(client)
     }
 
+    // 背景執行緒專門讀 stdout：有 `id` 的是某個掛起請求的回應，直接轉給對應的
+    // channel 喚醒呼叫端；沒有 `id` 的是通知 (例如 `notifications/progress`)，
+    // 一律轉發到 `notifications`，由呼叫端自行決定要不要消費。
+    fn spawn_reader(
+        stdout: std::process::ChildStdout,
+        pending: PendingMap,
+        notif_tx: Sender<JsonRpcNotification>,
+    ) -> JoinHandle<()> {
+        thread::spawn(move || {
+            let mut reader = BufReader::new(stdout);
+            let mut line = String::new();
+            loop {
+                line.clear();
+                match reader.read_line(&mut line) {
+                    Ok(0) | Err(_) => break, // EOF 或讀取錯誤，child process 大概掛了
+                    Ok(_) => {}
+                }
+                if line.trim().is_empty() {
+                    continue;
+                }
+
+                if let Ok(response) = serde_json::from_str::<JsonRpcResponse>(&line) {
+                    if let Some(id) = response.id {
+                        if let Some(sender) = pending.lock().unwrap().remove(&id) {
+                            let _ = sender.send(response);
+                        }
+                        continue;
+                    }
+                }
+
+                if let Ok(notification) = serde_json::from_str::<JsonRpcNotification>(&line) {
+                    let _ = notif_tx.send(notification);
+                }
+            }
+
+            // 迴圈結束代表 child process 已經掛了：把所有還卡在 `pending` 裡等回應的
+            // sender 清空。對應的 `request_response()` 呼叫端正卡在 `rx.recv()`，
+            // sender 被 drop 後 `recv()` 會立刻回傳 Err，而不是永遠卡住。
+            for (_, sender) in pending.lock().unwrap().drain() {
+                drop(sender);
+            }
+        })
+    }
+
+    /// 非阻塞地拿一筆待處理的通知 (例如 `notifications/progress`)；沒有就回傳 None。
+    pub fn try_recv_notification(&self) -> Option<JsonRpcNotification> {
+        self.notifications.lock().unwrap().try_recv().ok()
+    }
+
     fn initialize(&mut self) -> Result<()> {
         let params = InitializeParams {
             protocol_version: "2024-11-05".to_string(),
@@ -34,19 +107,17 @@ impl McpClient {
             },
         };
 
-        self.send_request("initialize", Some(serde_json::to_value(params)?))?;
-        let _resp = self.read_response()?; 
-        
+        let _resp = self.request_response("initialize", Some(serde_json::to_value(params)?))?;
+
         // MCP requires a notification after init
         self.send_notification("notifications/initialized", None)?;
-        
+
         Ok(())
     }
 
     pub fn list_tools(&mut self) -> Result<ListToolsResult> {
-        self.send_request("tools/list", None)?;
-        let resp = self.read_response()?;
-        
+        let resp = self.request_response("tools/list", None)?;
+
         if let Some(res) = resp.result {
             let tools: ListToolsResult = serde_json::from_value(res)?;
             Ok(tools)
@@ -60,10 +131,9 @@ impl McpClient {
             name: name.to_string(),
             arguments: args,
         };
-        
-        self.send_request("tools/call", Some(serde_json::to_value(params)?))?;
-        let resp = self.read_response()?;
-        
+
+        let resp = self.request_response("tools/call", Some(serde_json::to_value(params)?))?;
+
         if let Some(err) = resp.error {
             Err(anyhow::anyhow!("MCP Error {}: {}", err.code, err.message))
         } else if let Some(res) = resp.result {
@@ -73,22 +143,27 @@ impl McpClient {
         }
     }
 
-    fn send_request(&mut self, method: &str, params: Option<Value>) -> Result<()> {
+    // 送出請求後，在背景執行緒把對應的回應轉進來之前都卡在這個 channel 上；
+    // 不用再自己去 read_line 搶著讀 stdout，也不怕跟通知的訊息互相干擾。
+    fn request_response(&mut self, method: &str, params: Option<Value>) -> Result<JsonRpcResponse> {
         let id = self.next_id;
         self.next_id += 1;
-        
+
+        let (tx, rx) = mpsc::channel();
+        self.pending.lock().unwrap().insert(id, tx);
+
         let req = JsonRpcRequest {
             jsonrpc: "2.0".to_string(),
             method: method.to_string(),
             params,
             id,
         };
-        
-        let stdin = self.child.stdin.as_mut().context("Failed to get stdin")?;
+
         let json = serde_json::to_string(&req)?;
-        writeln!(stdin, "{}", json)?;
-        stdin.flush()?;
-        Ok(())
+        writeln!(self.stdin, "{}", json)?;
+        self.stdin.flush()?;
+
+        rx.recv().context("MCP server closed connection before responding")
     }
 
     fn send_notification(&mut self, method: &str, params: Option<Value>) -> Result<()> {
@@ -102,40 +177,22 @@ impl McpClient {
             #[serde(skip_serializing_if = "Option::is_none")]
             params: Option<Value>,
         }
-        
+
         let notif = Notification {
             jsonrpc: "2.0".to_string(),
             method: method.to_string(),
             params,
         };
-        
-        let stdin = self.child.stdin.as_mut().context("Failed to get stdin")?;
+
         let json = serde_json::to_string(&notif)?;
-        writeln!(stdin, "{}", json)?;
-        stdin.flush()?;
+        writeln!(self.stdin, "{}", json)?;
+        self.stdin.flush()?;
         Ok(())
     }
-
-    fn read_response(&mut self) -> Result<JsonRpcResponse> {
-        let stdout = self.child.stdout.as_mut().context("Failed to get stdout")?;
-        let mut reader = BufReader::new(stdout);
-        let mut line = String::new();
-        reader.read_line(&mut line)?;
-        
-        if line.is_empty() {
-            return Err(anyhow::anyhow!("MCP Server closed connection unexpectedly"));
-        }
-
-        // dbg!(&line); // Debug
-        
-        let response: JsonRpcResponse = serde_json::from_str(&line)
-            .context(format!("Failed to parse MCP response: {}", line))?;
-        Ok(response)
-    }
 }
 
 impl Drop for McpClient {
     fn drop(&mut self) {
         let _ = self.child.kill();
     }
-}
\ No newline at end of file
+}
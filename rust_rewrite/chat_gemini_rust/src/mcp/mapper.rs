@@ -23,31 +23,97 @@ pub fn map_mcp_tools_to_gemini(server_name: &str, mcp_tools: &ListToolsResult) -
 }
 
 fn map_json_schema(input: &Value) -> Schema {
-    // Simplified mapping from JSON Schema (Draft 7/2020-12) to Gemini Schema
-    // Gemini supports a subset of OpenAPI 3.0 schema
-    
-    let schema_type = input.get("type").and_then(|v| v.as_str()).unwrap_or("OBJECT").to_string().to_uppercase();
-    
-    let mut properties = None;
+    // Simplified mapping from JSON Schema (Draft 7/2020-12) to Gemini Schema.
+    // Gemini supports a subset of OpenAPI 3.0 schema, so real-world MCP tools
+    // (array/enum/format/anyOf...) need a best-effort collapse down to that subset
+    // or function calling silently mis-calls / rejects them.
+
+    // anyOf/oneOf：沒有真正的聯集型別可用，挑第一個分支當作最貼近的單一型別，
+    // 並在 description 附註共有幾種可能型別；其餘分支的內容不會帶過去。
+    if let Some(variants) = input.get("anyOf").or_else(|| input.get("oneOf")).and_then(|v| v.as_array()) {
+        if let Some(first) = variants.first() {
+            let mut schema = map_json_schema(first);
+            if variants.len() > 1 {
+                let note = format!("(anyOf: 共 {} 種可能型別，此處僅取第一種)", variants.len());
+                schema.description = Some(match schema.description.take() {
+                    Some(existing) => format!("{} {}", existing, note),
+                    None => note,
+                });
+            }
+            return schema;
+        }
+    }
+
+    // JSON Schema 允許 "type" 是字串陣列來表示可為 null (e.g. ["string", "null"])，
+    // Gemini 的 Schema.type 只接受單一字串，所以挑出非 null 的那個型別，nullable 另外標記。
+    let (schema_type, nullable_from_type) = match input.get("type") {
+        Some(Value::Array(types)) => {
+            let has_null = types.iter().any(|t| t.as_str() == Some("null"));
+            let primary = types.iter()
+                .filter_map(|t| t.as_str())
+                .find(|s| *s != "null")
+                .unwrap_or("OBJECT")
+                .to_string();
+            (primary, has_null)
+        }
+        Some(Value::String(s)) => (s.clone(), false),
+        _ => ("OBJECT".to_string(), false),
+    };
+    let mut schema = Schema::new(schema_type.to_uppercase());
+
+    let nullable = nullable_from_type || input.get("nullable").and_then(|v| v.as_bool()).unwrap_or(false);
+    if nullable {
+        schema.nullable = Some(true);
+    }
+
     if let Some(props) = input.get("properties").and_then(|v| v.as_object()) {
         let mut prop_map = HashMap::new();
         for (k, v) in props {
             prop_map.insert(k.clone(), map_json_schema(v));
         }
-        properties = Some(prop_map);
+        schema.properties = Some(prop_map);
     }
 
-    let mut required = None;
     if let Some(req) = input.get("required").and_then(|v| v.as_array()) {
         let req_vec: Vec<String> = req.iter().filter_map(|v| v.as_str().map(String::from)).collect();
         if !req_vec.is_empty() {
-            required = Some(req_vec);
+            schema.required = Some(req_vec);
+        }
+    }
+
+    if let Some(desc) = input.get("description").and_then(|v| v.as_str()) {
+        schema.description = Some(desc.to_string());
+    }
+
+    if let Some(format) = input.get("format").and_then(|v| v.as_str()) {
+        schema.format = Some(format.to_string());
+    }
+
+    if let Some(items) = input.get("items") {
+        schema.items = Some(Box::new(map_json_schema(items)));
+    }
+
+    if let Some(enum_values) = input.get("enum").and_then(|v| v.as_array()) {
+        let values: Vec<String> = enum_values.iter().map(json_scalar_to_string).collect();
+        if !values.is_empty() {
+            schema.r#enum = Some(values);
         }
     }
 
-    Schema {
-        schema_type,
-        properties,
-        required,
+    if let Some(min) = input.get("minimum").and_then(|v| v.as_f64()) {
+        schema.minimum = Some(min);
+    }
+    if let Some(max) = input.get("maximum").and_then(|v| v.as_f64()) {
+        schema.maximum = Some(max);
+    }
+
+    schema
+}
+
+// Gemini 的 enum 只接受字串，數字/布林列舉值一律轉成字串表示。
+fn json_scalar_to_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
     }
 }
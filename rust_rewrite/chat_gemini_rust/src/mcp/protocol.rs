@@ -49,6 +49,14 @@ pub struct JsonRpcError {
     pub data: Option<Value>,
 }
 
+// 沒有 id 的訊息 (例如 `notifications/progress`)，跟一般請求的回應分開處理。
+#[derive(Debug, Clone, Deserialize)]
+pub struct JsonRpcNotification {
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
 // --- Params ---
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -13,9 +13,10 @@ pub fn run_onboarding() -> Result<Settings> {
     println!("1. {} (推薦個人使用)", "輸入 Gemini API Key".cyan().bold());
     println!("2. {} (需要 client_secret.json)", "Google 帳號登入 (Standard OAuth)".yellow().bold());
     println!("3. {} (SSH/Headless Server)", "Google Device Flow".blue().bold());
-    println!("4. 離開\n");
+    println!("4. {} (免 client_secret)", "Google 帳號登入 (PKCE)".magenta().bold());
+    println!("5. 離開\n");
 
-    print!("請選擇 [1-4]: ");
+    print!("請選擇 [1-5]: ");
     io::stdout().flush()?;
 
     let mut input = String::new();
@@ -26,10 +27,43 @@ pub fn run_onboarding() -> Result<Settings> {
         "1" => setup_api_key(),
         "2" => setup_oauth("installed"),
         "3" => setup_oauth("device"),
+        "4" => setup_pkce(),
         _ => Err(anyhow::anyhow!("操作已取消")),
     }
 }
 
+// 只需要 OAuth client 的 `client_id`（沒有 client_secret 也能跑，這就是 PKCE 的重點）；
+// 實際互動登入 (開瀏覽器、本機監聽回呼) 留給 `GoogleAuth::new` 在偵測到這個設定時去做。
+fn setup_pkce() -> Result<Settings> {
+    print!("\n請輸入 OAuth Client ID (不需要 client_secret): ");
+    io::stdout().flush()?;
+
+    let mut client_id = String::new();
+    io::stdin().read_line(&mut client_id)?;
+    let client_id = client_id.trim().to_string();
+
+    if client_id.is_empty() {
+        return Err(anyhow::anyhow!("Client ID 不能為空"));
+    }
+
+    print!("是否儲存至 .env 檔案以供未來使用？ (y/n): ");
+    io::stdout().flush()?;
+    let mut save = String::new();
+    io::stdin().read_line(&mut save)?;
+
+    if save.trim().eq_ignore_ascii_case("y") {
+        let content = format!("GEMINI_OAUTH_PKCE_CLIENT_ID={}\nGEMINI_MODEL=gemini-2.0-flash\nGEMINI_LANG=zh-TW\n", client_id);
+        fs::write(".env", content)?;
+        println!("{}", "設定已儲存至 .env".green());
+    }
+
+    unsafe {
+        std::env::set_var("GEMINI_OAUTH_PKCE_CLIENT_ID", &client_id);
+    }
+
+    Settings::new().map_err(|e| anyhow::anyhow!(e))
+}
+
 fn setup_api_key() -> Result<Settings> {
     print!("\n請輸入您的 Gemini API Key: ");
     io::stdout().flush()?;